@@ -0,0 +1,60 @@
+//! Scripted kernel client.
+//!
+//! Sends every line read from stdin to a kernel listening on a Unix socket
+//! and prints the replies:
+//!
+//! ```sh
+//! cargo run -- --socket /tmp/kernel.sock &
+//! printf '(define x 2)\n(* x 21)\n:complete (+ x (le\n:shutdown\n' \
+//!     | cargo run --example client -- /tmp/kernel.sock
+//! ```
+//!
+//! A line is executed as code unless it is one of the commands
+//! `:complete CODE` (completes at the end of `CODE`), `:interrupt` or
+//! `:shutdown`.
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+};
+
+use serde_json::{json, Value};
+
+fn main() -> io::Result<()> {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("usage: client SOCKET");
+        std::process::exit(2);
+    };
+    let mut stream = UnixStream::connect(path)?;
+    let mut replies = BufReader::new(stream.try_clone()?).lines();
+
+    for (id, line) in io::stdin().lock().lines().enumerate() {
+        let line = line?;
+        let request = request(id, &line);
+        writeln!(stream, "{}", request)?;
+        let Some(reply) = replies.next() else {
+            break;
+        };
+        println!("{}", reply?);
+        if request["type"] == "shutdown_request" {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn request(id: usize, line: &str) -> Value {
+    if let Some(code) = line.strip_prefix(":complete ") {
+        return json!({
+            "id": id,
+            "type": "complete_request",
+            "code": code,
+            "cursor_pos": code.chars().count(),
+        });
+    }
+    match line {
+        ":interrupt" => json!({ "id": id, "type": "interrupt_request" }),
+        ":shutdown" => json!({ "id": id, "type": "shutdown_request" }),
+        code => json!({ "id": id, "type": "execute_request", "code": code }),
+    }
+}
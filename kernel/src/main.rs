@@ -0,0 +1,88 @@
+mod protocol;
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    os::unix::net::UnixListener,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+use protocol::{Flow, Kernel};
+
+fn main() -> io::Result<()> {
+    let mut kernel = Kernel::new();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => {
+            serve(&mut kernel, BufReader::new(io::stdin()), io::stdout())?;
+        }
+        ["--socket", path] => {
+            let listener = UnixListener::bind(path)?;
+            let res = serve_socket(&mut kernel, &listener);
+            let _ = std::fs::remove_file(path);
+            res?;
+        }
+        _ => {
+            eprintln!("usage: kernel [--socket PATH]");
+            std::process::exit(2);
+        }
+    }
+    Ok(())
+}
+
+// Serves one client at a time, sharing the session between them.
+fn serve_socket(kernel: &mut Kernel, listener: &UnixListener) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let reader = BufReader::new(stream.try_clone()?);
+        if serve(kernel, reader, stream)? == Flow::Shutdown {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// Requests are read on a separate thread, so interrupts are handled while
+// an execution is running.
+fn serve<R, W>(kernel: &mut Kernel, reader: R, writer: W) -> io::Result<Flow>
+where
+    R: 'static + Send + BufRead,
+    W: 'static + Send + Write,
+{
+    let writer = Arc::new(Mutex::new(writer));
+    let (sender, receiver) = mpsc::channel();
+    let interrupter = kernel.interrupter();
+    let reader_writer = writer.clone();
+    thread::spawn(move || -> io::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match interrupter.handle(&line) {
+                Some(reply) => write_reply(&reader_writer, &reply)?,
+                None => {
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(())
+    });
+
+    for line in receiver {
+        let (reply, flow) = kernel.handle(&line);
+        write_reply(&writer, &reply)?;
+        if flow == Flow::Shutdown {
+            return Ok(flow);
+        }
+    }
+    Ok(Flow::Continue)
+}
+
+fn write_reply(writer: &Mutex<impl Write>, reply: &serde_json::Value) -> io::Result<()> {
+    let mut writer = writer.lock().unwrap();
+    writeln!(writer, "{}", reply)?;
+    writer.flush()
+}
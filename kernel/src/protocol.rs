@@ -0,0 +1,297 @@
+//! Line-delimited JSON kernel protocol.
+//!
+//! Every request is one JSON object per line with a `type` and an optional
+//! `id`, echoed in the replies:
+//!
+//! ```json
+//! {"id": 1, "type": "execute_request", "code": "(define x 2) (* x 3)"}
+//! {"id": 2, "type": "complete_request", "code": "(+ x (le", "cursor_pos": 8}
+//! {"id": 3, "type": "interrupt_request"}
+//! {"id": 4, "type": "shutdown_request"}
+//! ```
+//!
+//! Replies are `execute_result` (`value` is `null` when the last form is a
+//! definition), `error` (with `kind`, `message` and character `span` into
+//! `code`), `complete_reply`, `interrupt_reply` and `shutdown_reply`.
+//!
+//! An `interrupt_request` stops the running execution, which replies with an
+//! `Interrupted` error. An execution request read before the interrupt but
+//! not started yet counts as running. It has no effect when nothing is
+//! running.
+
+use std::sync::{Arc, Mutex};
+
+use s_expression::{CancellationToken, Error, Limits, Session, Val};
+use serde_json::{json, Value};
+
+#[derive(Debug)]
+pub struct Kernel {
+    session: Session,
+    executions: Arc<Executions>,
+}
+
+/// Handles interrupt requests while the kernel is busy executing.
+#[derive(Debug, Clone)]
+pub struct Interrupter {
+    executions: Arc<Executions>,
+}
+
+// Execution requests, numbered from 1 in the order they are read. An
+// interrupt targets the oldest one not finished, and the token stays
+// cancelled until that one finishes, however early it arrives.
+#[derive(Debug, Default)]
+struct Executions {
+    token: CancellationToken,
+    counts: Mutex<Counts>,
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    received: u64,
+    finished: u64,
+    interrupted: Option<u64>,
+}
+
+/// What to do after handling a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    Shutdown,
+}
+
+impl Kernel {
+    pub fn new() -> Self {
+        let executions = Arc::new(Executions::default());
+        let mut session = Session::new();
+        session.set_limits(Limits::new().cancellation(executions.token.clone()));
+        Self {
+            session,
+            executions,
+        }
+    }
+
+    pub fn interrupter(&self) -> Interrupter {
+        Interrupter {
+            executions: self.executions.clone(),
+        }
+    }
+
+    pub fn handle(&mut self, line: &str) -> (Value, Flow) {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(err) => {
+                return (
+                    invalid_request(&Value::Null, err.to_string()),
+                    Flow::Continue,
+                )
+            }
+        };
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let reply = match request.get("type").and_then(Value::as_str) {
+            Some("execute_request") => {
+                let seq = self.executions.start();
+                let reply = match request.get("code").and_then(Value::as_str) {
+                    Some(code) => self.execute(&id, code),
+                    None => invalid_request(&id, "expect string field \"code\"".into()),
+                };
+                self.executions.finish(seq);
+                reply
+            }
+            Some("complete_request") => {
+                let code = request.get("code").and_then(Value::as_str);
+                let cursor_pos = request.get("cursor_pos").and_then(Value::as_u64);
+                match (code, cursor_pos) {
+                    (Some(code), Some(cursor_pos)) => self.complete(&id, code, cursor_pos as usize),
+                    _ => invalid_request(
+                        &id,
+                        "expect string field \"code\" and integer field \"cursor_pos\"".into(),
+                    ),
+                }
+            }
+            // Only reached when requests are not read while executing.
+            Some("interrupt_request") => self.interrupter().interrupt(&id),
+            Some("shutdown_request") => {
+                return (
+                    json!({ "id": id, "type": "shutdown_reply" }),
+                    Flow::Shutdown,
+                )
+            }
+            Some(ty) => invalid_request(&id, format!("unknown request type \"{}\"", ty)),
+            None => invalid_request(&id, "expect string field \"type\"".into()),
+        };
+        (reply, Flow::Continue)
+    }
+
+    fn execute(&mut self, id: &Value, code: &str) -> Value {
+        let res = self.session.execute(code);
+        let execution_count = self.session.execution_count();
+        match res {
+            Ok(val) => json!({
+                "id": id,
+                "type": "execute_result",
+                "execution_count": execution_count,
+                "value": val.map(value),
+            }),
+            Err(err) => json!({
+                "id": id,
+                "type": "error",
+                "execution_count": execution_count,
+                "kind": err.kind(),
+                "message": message(&err),
+                "span": { "start": err.span().start, "end": err.span().end },
+            }),
+        }
+    }
+
+    fn complete(&self, id: &Value, code: &str, cursor_pos: usize) -> Value {
+        let completion = self.session.complete(code, cursor_pos);
+        json!({
+            "id": id,
+            "type": "complete_reply",
+            "matches": completion.matches,
+            "cursor_start": completion.start,
+            "cursor_end": cursor_pos,
+        })
+    }
+}
+
+impl Interrupter {
+    /// Interrupts the running execution if `line` is an interrupt request,
+    /// returning the reply. Every request is to go through here as it is
+    /// read, before it is passed on to the `Kernel`.
+    pub fn handle(&self, line: &str) -> Option<Value> {
+        let request: Value = serde_json::from_str(line).ok()?;
+        match request.get("type").and_then(Value::as_str) {
+            Some("interrupt_request") => {
+                let id = request.get("id").cloned().unwrap_or(Value::Null);
+                Some(self.interrupt(&id))
+            }
+            Some("execute_request") => {
+                self.executions.receive();
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn interrupt(&self, id: &Value) -> Value {
+        self.executions.interrupt();
+        json!({ "id": id, "type": "interrupt_reply" })
+    }
+}
+
+impl Executions {
+    fn receive(&self) {
+        self.counts.lock().unwrap().received += 1;
+    }
+
+    // Number of the execution about to run.
+    fn start(&self) -> u64 {
+        let mut counts = self.counts.lock().unwrap();
+        let seq = counts.finished + 1;
+        // Not read through an `Interrupter`.
+        counts.received = counts.received.max(seq);
+        seq
+    }
+
+    fn finish(&self, seq: u64) {
+        let mut counts = self.counts.lock().unwrap();
+        counts.finished = seq;
+        if counts.interrupted == Some(seq) {
+            counts.interrupted = None;
+            self.token.reset();
+        }
+    }
+
+    fn interrupt(&self) {
+        let mut counts = self.counts.lock().unwrap();
+        if counts.finished < counts.received {
+            counts.interrupted = Some(counts.finished + 1);
+            self.token.cancel();
+        }
+    }
+}
+
+fn value(val: Val) -> Value {
+    match val {
+        Val::Int(val) => json!(val),
+        Val::Bool(val) => json!(val),
+    }
+}
+
+fn message(err: &Error) -> String {
+    match err {
+        Error::Parser(err) => err.msg.to_string(),
+        err => err.to_string(),
+    }
+}
+
+fn invalid_request(id: &Value, message: String) -> Value {
+    json!({
+        "id": id,
+        "type": "error",
+        "kind": "InvalidRequest",
+        "message": message,
+        "span": null,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXECUTE: &str = r#"{"id": 1, "type": "execute_request", "code": "(+ 1 2)"}"#;
+    const INTERRUPT: &str = r#"{"id": 2, "type": "interrupt_request"}"#;
+
+    // Passes `line` through the interrupter then the kernel, like `serve`.
+    fn send(kernel: &mut Kernel, interrupter: &Interrupter, line: &str) -> Value {
+        match interrupter.handle(line) {
+            Some(reply) => reply,
+            None => kernel.handle(line).0,
+        }
+    }
+
+    #[test]
+    fn interrupt_before_execution_starts() {
+        let mut kernel = Kernel::new();
+        let interrupter = kernel.interrupter();
+        // Read, but still queued when the interrupt arrives.
+        assert_eq!(interrupter.handle(EXECUTE), None);
+        assert_eq!(
+            interrupter.handle(INTERRUPT).unwrap()["type"],
+            "interrupt_reply"
+        );
+        assert_eq!(kernel.handle(EXECUTE).0["kind"], "Interrupted");
+        // Only that execution is interrupted.
+        assert_eq!(send(&mut kernel, &interrupter, EXECUTE)["value"], 3);
+    }
+
+    #[test]
+    fn interrupt_when_idle() {
+        let mut kernel = Kernel::new();
+        let interrupter = kernel.interrupter();
+        assert_eq!(send(&mut kernel, &interrupter, EXECUTE)["value"], 3);
+        assert_eq!(
+            send(&mut kernel, &interrupter, INTERRUPT)["type"],
+            "interrupt_reply"
+        );
+        assert_eq!(send(&mut kernel, &interrupter, EXECUTE)["value"], 3);
+
+        // Requests read without an interrupter.
+        assert_eq!(kernel.handle(INTERRUPT).0["type"], "interrupt_reply");
+        assert_eq!(kernel.handle(EXECUTE).0["value"], 3);
+    }
+
+    #[test]
+    fn interrupt_targets_the_oldest_execution() {
+        let mut kernel = Kernel::new();
+        let interrupter = kernel.interrupter();
+        let invalid = r#"{"id": 3, "type": "execute_request"}"#;
+        interrupter.handle(invalid);
+        interrupter.handle(EXECUTE);
+        interrupter.handle(INTERRUPT);
+        // The invalid request was the one interrupted.
+        assert_eq!(kernel.handle(invalid).0["kind"], "InvalidRequest");
+        assert_eq!(kernel.handle(EXECUTE).0["value"], 3);
+    }
+}
@@ -0,0 +1,115 @@
+use lsp_types::{Position, Range};
+
+/// Converts between the character offsets used by `s_expression` and LSP
+/// positions (line and UTF-16 column). Lines end with `\n`, `\r\n` or `\r`.
+pub struct LineIndex<'a> {
+    text: &'a str,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self { text }
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        let mut line = 0;
+        let mut character = 0;
+        let mut chars = self.text.chars().peekable();
+        for _ in 0..offset {
+            let Some(c) = chars.next() else { break };
+            match c {
+                // Counted at the `\n`.
+                '\r' if chars.peek() == Some(&'\n') => {}
+                '\n' | '\r' => {
+                    line += 1;
+                    character = 0;
+                }
+                c => character += c.len_utf16() as u32,
+            }
+        }
+        Position { line, character }
+    }
+
+    pub fn range(&self, span: std::ops::Range<usize>) -> Range {
+        Range {
+            start: self.position(span.start),
+            end: self.position(span.end),
+        }
+    }
+
+    pub fn offset(&self, position: Position) -> usize {
+        let mut line = 0;
+        let mut character = 0;
+        let mut chars = self.text.chars().enumerate().peekable();
+        while let Some((offset, c)) = chars.next() {
+            if line == position.line && character >= position.character {
+                return offset;
+            }
+            match c {
+                '\n' | '\r' => {
+                    // Columns past the end of the line clamp to it.
+                    if line == position.line {
+                        return offset;
+                    }
+                    if c == '\r' {
+                        chars.next_if(|(_, c)| *c == '\n');
+                    }
+                    line += 1;
+                    character = 0;
+                }
+                c => character += c.len_utf16() as u32,
+            }
+        }
+        self.text.chars().count()
+    }
+
+    pub fn end(&self) -> Position {
+        self.position(self.text.chars().count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(line: u32, character: u32) -> Position {
+        Position { line, character }
+    }
+
+    #[test]
+    fn utf16_columns() {
+        // `𝕏` is one char but two UTF-16 code units, `é` is one of each.
+        let text = "(𝕏 é)\n𝕏";
+        let index = LineIndex::new(text);
+        assert_eq!(index.position(1), pos(0, 1));
+        assert_eq!(index.position(2), pos(0, 3));
+        assert_eq!(index.position(4), pos(0, 5));
+        assert_eq!(index.position(6), pos(1, 0));
+        assert_eq!(index.end(), pos(1, 2));
+        for offset in 0..=text.chars().count() {
+            assert_eq!(index.offset(index.position(offset)), offset);
+        }
+        // The middle of a surrogate pair maps to the char after it.
+        assert_eq!(index.offset(pos(0, 2)), 2);
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let text = "(a\r\nb)\r\n\r\nc\rd";
+        let index = LineIndex::new(text);
+        assert_eq!(index.position(2), pos(0, 2));
+        // Between `\r` and `\n` is still the end of the line.
+        assert_eq!(index.position(3), pos(0, 2));
+        assert_eq!(index.position(4), pos(1, 0));
+        assert_eq!(index.position(10), pos(3, 0));
+        assert_eq!(index.position(12), pos(4, 0));
+        assert_eq!(index.range(4..6), Range::new(pos(1, 0), pos(1, 2)));
+        assert_eq!(index.offset(pos(1, 1)), 5);
+        assert_eq!(index.offset(pos(3, 0)), 10);
+        assert_eq!(index.offset(pos(4, 1)), 13);
+        // Past the end of a line, before its `\r\n`.
+        assert_eq!(index.offset(pos(0, 10)), 2);
+        assert_eq!(index.offset(pos(2, 10)), 8);
+        assert_eq!(index.offset(pos(9, 0)), text.chars().count());
+    }
+}
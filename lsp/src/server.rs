@@ -0,0 +1,334 @@
+use std::collections::HashMap;
+
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as NotificationTrait, PublishDiagnostics,
+    },
+    request::{Formatting, GotoDefinition, HoverRequest, Request as RequestTrait},
+    Diagnostic, DiagnosticSeverity, DocumentFormattingParams, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverContents, HoverParams, Location, MarkedString,
+    PublishDiagnosticsParams, TextEdit, Url,
+};
+use s_expression::{analysis, Error};
+
+use super::line_index::LineIndex;
+
+const FORMAT_WIDTH: usize = 80;
+
+type ServerResult<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<Url, String>,
+}
+
+impl Server {
+    pub fn run(mut self, connection: Connection) -> ServerResult<()> {
+        for msg in &connection.receiver {
+            match msg {
+                Message::Request(req) => {
+                    if connection.handle_shutdown(&req)? {
+                        return Ok(());
+                    }
+                    let resp = self.handle_request(req);
+                    connection.sender.send(Message::Response(resp))?;
+                }
+                Message::Notification(not) => match self.handle_notification(not) {
+                    Ok(nots) => {
+                        for not in nots {
+                            connection.sender.send(Message::Notification(not))?;
+                        }
+                    }
+                    // There is no one to answer a notification, a bad one is
+                    // logged and dropped.
+                    Err(err) => eprintln!("dropped notification: {}", err),
+                },
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    // Requests
+
+    fn handle_request(&self, req: Request) -> Response {
+        let id = req.id.clone();
+        let res = match req.method.as_str() {
+            HoverRequest::METHOD => {
+                extract::<HoverRequest>(req).and_then(|params| to_value(self.hover(params)))
+            }
+            GotoDefinition::METHOD => {
+                extract::<GotoDefinition>(req).and_then(|params| to_value(self.definition(params)))
+            }
+            Formatting::METHOD => {
+                extract::<Formatting>(req).and_then(|params| to_value(self.format(params)))
+            }
+            method => {
+                return Response::new_err(
+                    id,
+                    lsp_server::ErrorCode::MethodNotFound as i32,
+                    format!("unhandled method: {}", method),
+                )
+            }
+        };
+        match res {
+            Ok(val) => Response::new_ok(id, val),
+            Err(err) => error_response(id, err),
+        }
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let doc = &params.text_document_position_params;
+        let text = self.documents.get(&doc.text_document.uri)?;
+        let index = LineIndex::new(text);
+        let (span, val) = analysis::hover(text, index.offset(doc.position))?;
+        Some(Hover {
+            contents: HoverContents::Scalar(MarkedString::String(val.to_string())),
+            range: Some(index.range(span)),
+        })
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let doc = params.text_document_position_params;
+        let text = self.documents.get(&doc.text_document.uri)?;
+        let index = LineIndex::new(text);
+        let span = analysis::definition(text, index.offset(doc.position))?;
+        Some(GotoDefinitionResponse::Scalar(Location {
+            uri: doc.text_document.uri,
+            range: index.range(span),
+        }))
+    }
+
+    fn format(&self, params: DocumentFormattingParams) -> Option<Vec<TextEdit>> {
+        let text = self.documents.get(&params.text_document.uri)?;
+        let index = LineIndex::new(text);
+        let formatted = analysis::format(text, FORMAT_WIDTH).ok()?;
+        Some(vec![TextEdit {
+            range: lsp_types::Range {
+                start: index.position(0),
+                end: index.end(),
+            },
+            new_text: formatted + "\n",
+        }])
+    }
+
+    // Notifications
+
+    fn handle_notification(&mut self, not: Notification) -> ServerResult<Vec<Notification>> {
+        let uri = match not.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params = not.extract::<<DidOpenTextDocument as NotificationTrait>::Params>(
+                    DidOpenTextDocument::METHOD,
+                )?;
+                let doc = params.text_document;
+                self.documents.insert(doc.uri.clone(), doc.text);
+                doc.uri
+            }
+            DidChangeTextDocument::METHOD => {
+                let params = not.extract::<<DidChangeTextDocument as NotificationTrait>::Params>(
+                    DidChangeTextDocument::METHOD,
+                )?;
+                let uri = params.text_document.uri;
+                // Full sync: the last change holds the whole document.
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.documents.insert(uri.clone(), change.text);
+                }
+                uri
+            }
+            DidCloseTextDocument::METHOD => {
+                let params = not.extract::<<DidCloseTextDocument as NotificationTrait>::Params>(
+                    DidCloseTextDocument::METHOD,
+                )?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                return Ok(vec![publish_diagnostics(uri, Vec::new())]);
+            }
+            _ => return Ok(Vec::new()),
+        };
+        let text = &self.documents[&uri];
+        let index = LineIndex::new(text);
+        let diagnostics = analysis::diagnostics(text)
+            .iter()
+            .map(|err| diagnostic(&index, err))
+            .collect();
+        Ok(vec![publish_diagnostics(uri, diagnostics)])
+    }
+}
+
+fn diagnostic(index: &LineIndex, err: &Error) -> Diagnostic {
+    let message = match err {
+        Error::Parser(err) => err.msg.to_string(),
+        err => err.to_string(),
+    };
+    Diagnostic {
+        range: index.range(err.span()),
+        severity: Some(DiagnosticSeverity::ERROR),
+        code: Some(lsp_types::NumberOrString::String(err.kind().into())),
+        source: Some("s-expression".into()),
+        message,
+        ..Default::default()
+    }
+}
+
+fn publish_diagnostics(uri: Url, diagnostics: Vec<Diagnostic>) -> Notification {
+    Notification::new(
+        PublishDiagnostics::METHOD.into(),
+        PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version: None,
+        },
+    )
+}
+
+// Helpers
+
+fn extract<R: RequestTrait>(req: Request) -> ServerResult<R::Params> {
+    Ok(req
+        .extract::<R::Params>(R::METHOD)
+        .map(|(_, params)| params)?)
+}
+
+fn to_value<T: serde::Serialize>(val: T) -> ServerResult<serde_json::Value> {
+    Ok(serde_json::to_value(val)?)
+}
+
+fn error_response(id: RequestId, err: Box<dyn std::error::Error + Send + Sync>) -> Response {
+    Response::new_err(
+        id,
+        lsp_server::ErrorCode::InvalidParams as i32,
+        err.to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::{
+        Position, Range, TextDocumentIdentifier, TextDocumentPositionParams, WorkDoneProgressParams,
+    };
+
+    use super::*;
+
+    fn server(text: &str) -> (Server, Url) {
+        let uri = Url::parse("file:///test.scm").unwrap();
+        let mut server = Server::default();
+        server.documents.insert(uri.clone(), text.to_owned());
+        (server, uri)
+    }
+
+    fn at(uri: &Url, line: u32, character: u32) -> TextDocumentPositionParams {
+        TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            position: Position { line, character },
+        }
+    }
+
+    fn range(start: (u32, u32), end: (u32, u32)) -> Range {
+        Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1))
+    }
+
+    #[test]
+    fn hover_and_definition_across_crlf_and_surrogates() {
+        // `𝕏` is no token, and two UTF-16 code units.
+        let (server, uri) = server("𝕏 (define x 20)\r\n𝕏 (+ x (* x x))");
+        let hover = server
+            .hover(HoverParams {
+                text_document_position_params: at(&uri, 1, 9),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+            })
+            .unwrap();
+        assert_eq!(hover.range, Some(range((1, 8), (1, 15))));
+        assert!(matches!(
+            hover.contents,
+            HoverContents::Scalar(MarkedString::String(val)) if val == "400"
+        ));
+
+        let definition = server
+            .definition(GotoDefinitionParams {
+                text_document_position_params: at(&uri, 1, 13),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: Default::default(),
+            })
+            .unwrap();
+        let GotoDefinitionResponse::Scalar(location) = definition else {
+            panic!("expected a single location");
+        };
+        assert_eq!(location.range, range((0, 11), (0, 12)));
+    }
+
+    #[test]
+    fn diagnostics_ranges() {
+        let (mut server, uri) = server("");
+        let not = Notification::new(
+            DidOpenTextDocument::METHOD.into(),
+            lsp_types::DidOpenTextDocumentParams {
+                text_document: lsp_types::TextDocumentItem {
+                    uri,
+                    language_id: "scheme".into(),
+                    version: 0,
+                    text: "(define x 1)\r\n(if x 𝕏)".into(),
+                },
+            },
+        );
+        let nots = server.handle_notification(not).unwrap();
+        let params: PublishDiagnosticsParams =
+            serde_json::from_value(nots[0].params.clone()).unwrap();
+        let ranges: Vec<_> = params.diagnostics.iter().map(|diag| diag.range).collect();
+        // The bad token, and the missing branch after it, two UTF-16 units on.
+        assert_eq!(ranges, [range((1, 6), (1, 6)), range((1, 8), (1, 8))]);
+    }
+
+    #[test]
+    fn bad_notification_is_dropped() {
+        let (connection, client) = Connection::memory();
+        let server = std::thread::spawn(move || Server::default().run(connection));
+        let uri = Url::parse("file:///test.scm").unwrap();
+        client
+            .sender
+            .send(Message::Notification(Notification::new(
+                DidOpenTextDocument::METHOD.into(),
+                serde_json::json!({ "textDocument": { "uri": uri } }),
+            )))
+            .unwrap();
+        client
+            .sender
+            .send(Message::Notification(Notification::new(
+                DidOpenTextDocument::METHOD.into(),
+                lsp_types::DidOpenTextDocumentParams {
+                    text_document: lsp_types::TextDocumentItem {
+                        uri: uri.clone(),
+                        language_id: "scheme".into(),
+                        version: 0,
+                        text: "(+ 1 #)".into(),
+                    },
+                },
+            )))
+            .unwrap();
+        let Message::Notification(not) = client.receiver.recv().unwrap() else {
+            panic!("expected diagnostics");
+        };
+        let params: PublishDiagnosticsParams = serde_json::from_value(not.params).unwrap();
+        assert_eq!(params.uri, uri);
+        assert_eq!(params.diagnostics.len(), 1);
+
+        client
+            .sender
+            .send(Message::Request(Request::new(
+                RequestId::from(1),
+                "shutdown".into(),
+                serde_json::Value::Null,
+            )))
+            .unwrap();
+        assert!(matches!(client.receiver.recv(), Ok(Message::Response(_))));
+        client
+            .sender
+            .send(Message::Notification(Notification::new(
+                "exit".into(),
+                serde_json::Value::Null,
+            )))
+            .unwrap();
+        assert!(server.join().unwrap().is_ok());
+    }
+}
@@ -0,0 +1,304 @@
+//! Grammar macros for `parsec`.
+//!
+//! [`seq!`] and [`choice!`] read like the grammar they build, and expand to
+//! the adapters of `parsec::Parser`, so the parsers are typed as if chained
+//! by hand.
+//!
+//! The expansions name the crate by its absolute path `::parsec`, so a crate
+//! using the macros depends on `parsec` under that name, not renamed in its
+//! `Cargo.toml`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_macro_input,
+    punctuated::Punctuated,
+    spanned::Spanned,
+    Expr, Ident, Result, Token,
+};
+
+/// Parsers run one after another, their outputs combined by the expression
+/// after `=>`.
+///
+/// Items prefixed with `name:` bind their output to `name`, the outputs of
+/// the others are dropped. Without `=>` the output is the tuple of the bound
+/// names, or the single bound name, or `()`. Bare names are cloned, so a
+/// parser kept in a variable can be used more than once.
+///
+/// ```
+/// use parsec::{consumers::*, Context, Parser};
+/// use parsec_macros::seq;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Node<'a> {
+///     name: &'a str,
+///     body: i64,
+/// }
+///
+/// let ws = whitespaces();
+/// let node = seq!(char('('), ws, name: identifier(), ws, body: number(), char(')') => Node { name, body });
+/// assert_eq!(
+///     Context::from_str("( x 42)").parse_by(node).unwrap(),
+///     Node { name: "x", body: 42 }
+/// );
+///
+/// let pair = seq!(lhs: number(), char(','), rhs: number());
+/// assert_eq!(Context::from_str("1,2").parse_by(pair).unwrap(), (1, 2));
+/// ```
+///
+/// It expands to `pair` and `map`:
+///
+/// ```text
+/// seq!(char('('), name: identifier(), char(')') => name)
+/// ```
+///
+/// is
+///
+/// ```text
+/// char('(').pair(identifier()).pair(char(')')).map(|((_, name), _)| name)
+/// ```
+#[proc_macro]
+pub fn seq(input: TokenStream) -> TokenStream {
+    let seq = parse_macro_input!(input as Seq);
+    seq.expand().into()
+}
+
+/// The first of the parsers to succeed, which all have the same output.
+///
+/// ```
+/// use parsec::{consumers::*, Context, Parser};
+/// use parsec_macros::choice;
+///
+/// let bool = choice![
+///     string("true").map(|_| true),
+///     string("false").map(|_| false),
+/// ];
+/// assert!(!Context::from_str("false").parse_by(bool).unwrap());
+/// ```
+///
+/// It expands to `a.or(b).or(c)` for `choice![a, b, c]`.
+#[proc_macro]
+pub fn choice(input: TokenStream) -> TokenStream {
+    let choice = parse_macro_input!(input as Choice);
+    choice.expand().into()
+}
+
+// Seq
+
+struct Seq {
+    items: Vec<Item>,
+    output: Option<Expr>,
+}
+
+struct Item {
+    name: Option<Ident>,
+    parser: Expr,
+}
+
+impl Parse for Seq {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut items = Vec::new();
+        while !input.is_empty() && !input.peek(Token![=>]) {
+            items.push(input.parse()?);
+            if input.is_empty() || input.peek(Token![=>]) {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+        if items.is_empty() {
+            return Err(input.error("expected at least one parser"));
+        }
+        let output = if input.parse::<Option<Token![=>]>>()?.is_some() {
+            let output = input.parse()?;
+            input.parse::<Option<Token![,]>>()?;
+            Some(output)
+        } else {
+            None
+        };
+        Ok(Self { items, output })
+    }
+}
+
+impl Parse for Item {
+    fn parse(input: ParseStream) -> Result<Self> {
+        // `name: parser`, but not a path such as `consumers::char('(')`.
+        let name = if input.peek(Ident) && input.peek2(Token![:]) && !input.peek2(Token![::]) {
+            let name = input.parse()?;
+            input.parse::<Token![:]>()?;
+            Some(name)
+        } else {
+            None
+        };
+        let parser = input.parse()?;
+        Ok(Self { name, parser })
+    }
+}
+
+impl Seq {
+    fn expand(&self) -> TokenStream2 {
+        let mut items = self.items.iter();
+        let first = items.next().unwrap();
+        let mut parser = parser(&first.parser);
+        let mut pattern = binding(first);
+        for item in items {
+            let next = self::parser(&item.parser);
+            let binding = binding(item);
+            parser = quote!(::parsec::Parser::pair(#parser, #next));
+            pattern = quote!((#pattern, #binding));
+        }
+        let output = match &self.output {
+            Some(output) => quote!(#output),
+            None => {
+                let names: Vec<_> = self
+                    .items
+                    .iter()
+                    .filter_map(|item| item.name.as_ref())
+                    .collect();
+                match &names[..] {
+                    [name] => quote!(#name),
+                    names => quote!((#(#names),*)),
+                }
+            }
+        };
+        quote!(::parsec::Parser::map(#parser, |#pattern| #output))
+    }
+}
+
+fn binding(item: &Item) -> TokenStream2 {
+    match &item.name {
+        Some(name) => quote!(#name),
+        None => quote!(_),
+    }
+}
+
+// Choice
+
+struct Choice {
+    parsers: Punctuated<Expr, Token![,]>,
+}
+
+impl Parse for Choice {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let parsers = Punctuated::parse_terminated(input)?;
+        if parsers.is_empty() {
+            return Err(input.error("expected at least one parser"));
+        }
+        Ok(Self { parsers })
+    }
+}
+
+impl Choice {
+    fn expand(&self) -> TokenStream2 {
+        let mut parsers = self.parsers.iter().map(parser);
+        let first = parsers.next().unwrap();
+        parsers.fold(
+            first,
+            |left, right| quote!(::parsec::Parser::or(#left, #right)),
+        )
+    }
+}
+
+// Helpers
+
+// A bare name is cloned rather than moved, it may be used again.
+fn parser(expr: &Expr) -> TokenStream2 {
+    match expr {
+        Expr::Path(path) if path.qself.is_none() && path.path.get_ident().is_some() => {
+            quote_spanned!(expr.span()=> ::core::clone::Clone::clone(&#expr))
+        }
+        _ => quote!(#expr),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seq(input: &str) -> String {
+        syn::parse_str::<Seq>(input).unwrap().expand().to_string()
+    }
+
+    fn choice(input: &str) -> String {
+        syn::parse_str::<Choice>(input)
+            .unwrap()
+            .expand()
+            .to_string()
+    }
+
+    fn expected(tokens: TokenStream2) -> String {
+        tokens.to_string()
+    }
+
+    fn seq_error(input: &str) -> String {
+        syn::parse_str::<Seq>(input).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn seq_expansion() {
+        assert_eq!(
+            seq("char('('), name: identifier(), char(')') => name"),
+            expected(quote!(::parsec::Parser::map(
+                ::parsec::Parser::pair(::parsec::Parser::pair(char('('), identifier()), char(')')),
+                |((_, name), _)| name
+            )))
+        );
+        assert_eq!(
+            seq("lhs: number(), char(','), rhs: number(),"),
+            expected(quote!(::parsec::Parser::map(
+                ::parsec::Parser::pair(::parsec::Parser::pair(number(), char(',')), number()),
+                |((lhs, _), rhs)| (lhs, rhs)
+            )))
+        );
+        assert_eq!(
+            seq("char('a')"),
+            expected(quote!(::parsec::Parser::map(char('a'), |_| ())))
+        );
+    }
+
+    #[test]
+    fn names_and_paths() {
+        // Bare names are cloned, paths are not names.
+        assert_eq!(
+            seq("ws, x: consumers::char('a'), ws => x"),
+            expected(quote!(::parsec::Parser::map(
+                ::parsec::Parser::pair(
+                    ::parsec::Parser::pair(::core::clone::Clone::clone(&ws), consumers::char('a')),
+                    ::core::clone::Clone::clone(&ws)
+                ),
+                |((_, x), _)| x
+            )))
+        );
+    }
+
+    #[test]
+    fn choice_expansion() {
+        assert_eq!(
+            choice("a(), b, c(),"),
+            expected(quote!(::parsec::Parser::or(
+                ::parsec::Parser::or(a(), ::core::clone::Clone::clone(&b)),
+                c()
+            )))
+        );
+        assert_eq!(choice("a()"), expected(quote!(a())));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            seq_error(""),
+            "unexpected end of input, expected at least one parser"
+        );
+        assert_eq!(seq_error("=> x"), "expected at least one parser");
+        assert_eq!(seq_error("a() b()"), "expected `,`");
+        assert_eq!(
+            seq_error("a() =>"),
+            "unexpected end of input, expected an expression"
+        );
+        assert_eq!(
+            syn::parse_str::<Choice>("").err().unwrap().to_string(),
+            "unexpected end of input, expected at least one parser"
+        );
+    }
+}
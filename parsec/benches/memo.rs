@@ -0,0 +1,58 @@
+//! Parses nested S-expressions with a grammar that backtracks over whole
+//! lists: a list is tried first with a dotted tail `(a b) . c`, then without.
+//! Unmemoized, every level parses its sublist twice, so time doubles with
+//! each level of nesting; with `memo()` it grows linearly.
+//!
+//! The S-expression parser itself never backtracks, its own bench is
+//! `cargo bench -p s-expression --bench parse`.
+//!
+//! Run with `cargo bench --bench memo`.
+
+use std::time::Instant;
+
+use parsec::{consumers::*, recursive, Any, Context, Parser, StrStream};
+
+// Counts the atoms of one S-expression.
+fn sexp<'a>(memo: bool) -> Any<'a, StrStream<'a>, usize> {
+    recursive(|sexp| {
+        let spaces = || take_while(|c: &char| c.is_whitespace());
+        let atom = identifier().map(|_| 1).use_left(spaces());
+        let list = sexp
+            .many()
+            .map(|atoms| atoms.into_iter().sum())
+            .between(char('(').use_left(spaces()), char(')').use_left(spaces()));
+        let dotted = list
+            .clone()
+            .use_left(char('.').use_left(spaces()))
+            .pair(atom.clone())
+            .map(|(lhs, rhs)| lhs + rhs);
+        let sexp = dotted.or(list).or(atom);
+        if memo {
+            sexp.memo().to_any()
+        } else {
+            sexp.to_any()
+        }
+    })
+    .to_any()
+}
+
+fn nested(depth: usize) -> String {
+    format!("{}x{}", "(a ".repeat(depth), ")".repeat(depth))
+}
+
+fn bench(name: &str, depth: usize, memo: bool) {
+    let input = nested(depth);
+    let start = Instant::now();
+    let atoms = Context::from_str(&input).parse_by(sexp(memo)).unwrap();
+    assert_eq!(atoms, depth + 1);
+    println!("{:>8} depth {:>5}: {:?}", name, depth, start.elapsed());
+}
+
+fn main() {
+    for depth in [4, 8, 12, 16, 20] {
+        bench("plain", depth, false);
+    }
+    for depth in [4, 8, 12, 16, 20, 100, 1_000] {
+        bench("memo", depth, true);
+    }
+}
@@ -0,0 +1,95 @@
+//! Parses nested S-expressions such as `(+ 1 (- 2 (if true 3 4)))` with the
+//! same grammar built two ways: rebuilt at every level of nesting inside
+//! `flat_map`, and built once with `recursive`. Counts the allocations made
+//! while parsing.
+//!
+//! The infix front-end of s-expression is built with `recursive`, see
+//! `cargo bench -p s-expression --features infix --bench parse`.
+//!
+//! Run with `cargo bench --bench recursive`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use parsec::{consumers::*, just, recursive, Any, Context, Parser, StrStream};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+type Sexp<'a> = Any<'a, StrStream<'a>, usize>;
+
+// Counts the atoms of an expression.
+fn grammar<'a>(exp: impl 'a + Parser<Input = StrStream<'a>, Output = usize>) -> Sexp<'a> {
+    let spaces = || take_while(|c: &char| c.is_whitespace());
+    let token = move |str| tag(str).use_left(spaces());
+    let args = |n| {
+        let exp = exp.clone();
+        (0..n)
+            .map(move |_| exp.clone().to_any())
+            .reduce(|lhs, rhs| lhs.pair(rhs).map(|(lhs, rhs)| lhs + rhs).to_any())
+            .unwrap()
+    };
+    let oper = token("+").or(token("-")).or(token("*")).use_right(args(2));
+    let if_exp = token("if").use_right(args(3));
+    let list = if_exp.or(oper).between(token("("), token(")"));
+    let atom = number().map(|_| 1).or(bool().map(|_| 1)).use_left(spaces());
+    list.or(atom).to_any()
+}
+
+fn rebuilt<'a>() -> Sexp<'a> {
+    grammar(just(|| ()).flat_map(|_| rebuilt()))
+}
+
+fn shared<'a>() -> Sexp<'a> {
+    recursive(grammar).to_any()
+}
+
+fn nested(depth: usize) -> String {
+    let mut input = String::from("1");
+    for level in 0..depth {
+        input = match level % 2 {
+            0 => format!("(+ {} 2)", input),
+            _ => format!("(if true {} 3)", input),
+        };
+    }
+    input
+}
+
+fn bench(name: &str, depth: usize, parser: fn() -> Sexp<'static>) {
+    let input: &'static str = Box::leak(nested(depth).into_boxed_str());
+    let start = Instant::now();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let atoms = Context::from_str(input).parse_by(parser()).unwrap();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    let elapsed = start.elapsed();
+    assert!(atoms > depth);
+    println!(
+        "{:>9} depth {:>4}: {:>9} allocations, {:?}",
+        name, depth, allocations, elapsed
+    );
+}
+
+fn main() {
+    for depth in [10, 100, 1_000] {
+        bench("rebuilt", depth, rebuilt);
+        bench("recursive", depth, shared);
+    }
+}
@@ -1,6 +1,6 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, ops::Range};
 
-use super::{Context, Parser, Result};
+use super::{context::next_memo_id, Context, Parser, Result, Sliceable, Stream};
 
 /// Map
 #[derive(Debug, Clone)]
@@ -14,9 +14,10 @@ where
     Parent: Parser,
     F: Clone + Fn(Parent::Output) -> Output,
 {
+    type Input = Parent::Input;
     type Output = Output;
 
-    fn parse_raw(&self, context: &mut Context) -> Result<Self::Output> {
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
         self.parent.parse(context).map(&self.f)
     }
 }
@@ -34,9 +35,10 @@ where
     Error: Into<Cow<'static, str>>,
     F: Clone + Fn(Parent::Output) -> std::result::Result<Output, Error>,
 {
+    type Input = Parent::Input;
     type Output = Output;
 
-    fn parse_raw(&self, context: &mut Context) -> Result<Self::Output> {
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
         self.parent
             .parse(context)
             .and_then(|val| (self.f)(val).map_err(|e| context.throw_parser_err(e)))
@@ -54,11 +56,12 @@ impl<Parent, F, Next> Parser for FlatMap<Parent, F>
 where
     Parent: Parser,
     F: Clone + Fn(Parent::Output) -> Next,
-    Next: Parser,
+    Next: Parser<Input = Parent::Input>,
 {
+    type Input = Parent::Input;
     type Output = Next::Output;
 
-    fn parse_raw(&self, context: &mut Context) -> Result<Self::Output> {
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
         self.parent
             .parse(context)
             .and_then(|x| (self.f)(x).parse(context))
@@ -75,11 +78,12 @@ pub struct Pair<Left, Right> {
 impl<Left, Right> Parser for Pair<Left, Right>
 where
     Left: Parser,
-    Right: Parser,
+    Right: Parser<Input = Left::Input>,
 {
+    type Input = Left::Input;
     type Output = (Left::Output, Right::Output);
 
-    fn parse_raw(&self, context: &mut Context) -> Result<Self::Output> {
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
         let left = self.left.parse(context)?;
         let right = self.right.parse(context)?;
         Ok((left, right))
@@ -96,16 +100,15 @@ pub struct UseLeft<Left, Right> {
 impl<Left, Right> Parser for UseLeft<Left, Right>
 where
     Left: Parser,
-    Right: Parser,
+    Right: Parser<Input = Left::Input>,
 {
+    type Input = Left::Input;
     type Output = Left::Output;
 
-    fn parse_raw(&self, context: &mut Context) -> Result<Self::Output> {
-        self.left
-            .clone()
-            .pair(self.right.clone())
-            .map(|(val, _)| val)
-            .parse(context)
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        let val = self.left.parse(context)?;
+        self.right.parse(context)?;
+        Ok(val)
     }
 }
 
@@ -119,16 +122,14 @@ pub struct UseRight<Left, Right> {
 impl<Left, Right> Parser for UseRight<Left, Right>
 where
     Left: Parser,
-    Right: Parser,
+    Right: Parser<Input = Left::Input>,
 {
+    type Input = Left::Input;
     type Output = Right::Output;
 
-    fn parse_raw(&self, context: &mut Context) -> Result<Self::Output> {
-        self.left
-            .clone()
-            .pair(self.right.clone())
-            .map(|(_, val)| val)
-            .parse(context)
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        self.left.parse(context)?;
+        self.right.parse(context)
     }
 }
 
@@ -143,17 +144,17 @@ pub struct Between<Parent, Left, Right> {
 impl<Parent, Left, Right> Parser for Between<Parent, Left, Right>
 where
     Parent: Parser,
-    Left: Parser,
-    Right: Parser,
+    Left: Parser<Input = Parent::Input>,
+    Right: Parser<Input = Parent::Input>,
 {
+    type Input = Parent::Input;
     type Output = Parent::Output;
 
-    fn parse_raw(&self, context: &mut Context) -> Result<Self::Output> {
-        self.left
-            .clone()
-            .use_right(self.parent.clone())
-            .use_left(self.right.clone())
-            .parse(context)
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        self.left.parse(context)?;
+        let val = self.parent.parse(context)?;
+        self.right.parse(context)?;
+        Ok(val)
     }
 }
 
@@ -167,11 +168,12 @@ pub struct Or<Left, Right> {
 impl<Left, Right> Parser for Or<Left, Right>
 where
     Left: Parser,
-    Right: Parser<Output = Left::Output>,
+    Right: Parser<Input = Left::Input, Output = Left::Output>,
 {
+    type Input = Left::Input;
     type Output = Right::Output;
 
-    fn parse_raw(&self, context: &mut Context) -> Result<Self::Output> {
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
         match self.left.parse(context) {
             Ok(val) => Ok(val),
             Err(left_err) => self.right.parse(context).map_err(|right_err| {
@@ -185,6 +187,74 @@ where
     }
 }
 
+// Runs the parser at least `min` and at most `max` times, folding the
+// outputs into `acc`. Once `min` is reached, a match of the empty input ends
+// the repetition and is undone, as it would otherwise match forever.
+fn repeat<P, A, F>(
+    parser: &P,
+    context: &mut Context<P::Input>,
+    (min, max): (usize, usize),
+    mut acc: A,
+    mut f: F,
+) -> Result<A>
+where
+    P: Parser,
+    F: FnMut(A, P::Output) -> A,
+{
+    let mut count = 0;
+    while count < max {
+        let checkpoint = context.checkpoint();
+        let (pos, errors) = (context.pos(), context.errors().len());
+        let val = match parser.parse(context) {
+            Ok(val) => val,
+            Err(err) if count < min => return Err(err),
+            Err(_) => break,
+        };
+        if count >= min && context.pos() == pos {
+            context.reset(checkpoint);
+            context.truncate_errors(errors);
+            break;
+        }
+        acc = f(acc, val);
+        count += 1;
+    }
+    Ok(acc)
+}
+
+fn push<T>(mut vec: Vec<T>, val: T) -> Vec<T> {
+    vec.push(val);
+    vec
+}
+
+// `left` then `right`, borrowed from the parser being run rather than cloned
+// for every parse.
+#[derive(Debug)]
+struct PairRef<'p, Left, Right> {
+    left: &'p Left,
+    right: &'p Right,
+}
+
+impl<Left, Right> Clone for PairRef<'_, Left, Right> {
+    fn clone(&self) -> Self {
+        Self { ..*self }
+    }
+}
+
+impl<Left, Right> Parser for PairRef<'_, Left, Right>
+where
+    Left: Parser,
+    Right: Parser<Input = Left::Input>,
+{
+    type Input = Left::Input;
+    type Output = (Left::Output, Right::Output);
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        let left = self.left.parse(context)?;
+        let right = self.right.parse(context)?;
+        Ok((left, right))
+    }
+}
+
 /// Some
 #[derive(Debug, Clone)]
 pub struct Some<Parent> {
@@ -195,15 +265,11 @@ impl<Parent> Parser for Some<Parent>
 where
     Parent: Parser,
 {
+    type Input = Parent::Input;
     type Output = Vec<Parent::Output>;
 
-    fn parse_raw(&self, context: &mut Context) -> Result<Self::Output> {
-        let first = self.parent.parse(context)?;
-        let mut output = vec![first];
-        while let Ok(val) = self.parent.parse(context) {
-            output.push(val)
-        }
-        Ok(output)
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        repeat(&self.parent, context, (1, usize::MAX), Vec::new(), push)
     }
 }
 
@@ -217,18 +283,109 @@ impl<Parent> Parser for Many<Parent>
 where
     Parent: Parser,
 {
+    type Input = Parent::Input;
     type Output = Vec<Parent::Output>;
 
-    fn parse_raw(&self, context: &mut Context) -> Result<Self::Output> {
-        if let Ok(some) = self.parent.clone().some().parse(context) {
-            Ok(some)
-        } else {
-            Ok(Vec::new())
-        }
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        repeat(&self.parent, context, (0, usize::MAX), Vec::new(), push)
+    }
+}
+
+/// Repeat
+///
+/// Between `min` and `max` matches, see `count` and `many_m_n`.
+#[derive(Debug, Clone)]
+pub struct Repeat<Parent> {
+    pub(super) parent: Parent,
+    pub(super) min: usize,
+    pub(super) max: usize,
+}
+
+impl<Parent> Parser for Repeat<Parent>
+where
+    Parent: Parser,
+{
+    type Input = Parent::Input;
+    type Output = Vec<Parent::Output>;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        let range = (self.min, self.max);
+        repeat(&self.parent, context, range, Vec::new(), push)
+    }
+}
+
+/// FoldMany
+///
+/// Like `Many`, but folds the outputs instead of collecting them.
+#[derive(Debug, Clone)]
+pub struct FoldMany<Parent, Init, F> {
+    pub(super) parent: Parent,
+    pub(super) init: Init,
+    pub(super) f: F,
+}
+
+impl<Parent, Init, F, Acc> Parser for FoldMany<Parent, Init, F>
+where
+    Parent: Parser,
+    Init: Clone + Fn() -> Acc,
+    F: Clone + Fn(Acc, Parent::Output) -> Acc,
+{
+    type Input = Parent::Input;
+    type Output = Acc;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        repeat(
+            &self.parent,
+            context,
+            (0, usize::MAX),
+            (self.init)(),
+            &self.f,
+        )
+    }
+}
+
+/// SkipMany
+///
+/// Like `Many`, but drops the outputs.
+#[derive(Debug, Clone)]
+pub struct SkipMany<Parent> {
+    pub(super) parent: Parent,
+}
+
+impl<Parent> Parser for SkipMany<Parent>
+where
+    Parent: Parser,
+{
+    type Input = Parent::Input;
+    type Output = ();
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        repeat(&self.parent, context, (0, usize::MAX), (), |_, _| ())
+    }
+}
+
+/// Optional
+#[derive(Debug, Clone)]
+pub struct Optional<Parent> {
+    pub(super) parent: Parent,
+}
+
+impl<Parent> Parser for Optional<Parent>
+where
+    Parent: Parser,
+{
+    type Input = Parent::Input;
+    type Output = Option<Parent::Output>;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        Ok(self.parent.parse(context).ok())
     }
 }
 
 /// ManyTill
+///
+/// Fails with the error of `end` where the parent matches without consuming
+/// any input, as `end` would never be tried anywhere else.
 #[derive(Debug, Clone)]
 pub struct ManyTill<Parent, End> {
     pub(super) parent: Parent,
@@ -238,17 +395,24 @@ pub struct ManyTill<Parent, End> {
 impl<Parent, End> Parser for ManyTill<Parent, End>
 where
     Parent: Parser,
-    End: Parser,
+    End: Parser<Input = Parent::Input>,
 {
+    type Input = Parent::Input;
     type Output = Vec<Parent::Output>;
 
-    fn parse_raw(&self, context: &mut Context) -> Result<Self::Output> {
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
         let mut res = Vec::new();
-        while self.end.parse(context).is_err() {
-            let val = self.parent.parse(context)?;
-            res.push(val);
+        loop {
+            let err = match self.end.parse(context) {
+                Ok(_) => return Ok(res),
+                Err(err) => err,
+            };
+            let pos = context.pos();
+            res.push(self.parent.parse(context)?);
+            if context.pos() == pos {
+                return Err(err);
+            }
         }
-        Ok(res)
     }
 }
 
@@ -264,9 +428,10 @@ where
     Parent: Parser,
     F: Clone + Fn(&Parent::Output) -> bool,
 {
+    type Input = Parent::Input;
     type Output = Parent::Output;
 
-    fn parse_raw(&self, context: &mut Context) -> Result<Self::Output> {
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
         self.parent.parse(context).and_then(|val| {
             if (self.f)(&val) {
                 Ok(val)
@@ -287,23 +452,159 @@ pub struct Split<Parent, Sep> {
 impl<Parent, Sep> Parser for Split<Parent, Sep>
 where
     Parent: Parser,
-    Sep: Parser,
+    Sep: Parser<Input = Parent::Input>,
 {
+    type Input = Parent::Input;
     type Output = Vec<Parent::Output>;
 
-    fn parse_raw(&self, context: &mut Context) -> Result<Self::Output> {
-        let first = self.parent.parse(context)?;
-        let mut res = self
-            .sep
-            .clone()
-            .use_right(self.parent.clone())
-            .many()
-            .parse(context)?;
-        res.insert(0, first);
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        split(&self.parent, &self.sep, context)
+    }
+}
+
+// Items of `Split` and `SepBy`.
+fn split<P, Sep>(parent: &P, sep: &Sep, context: &mut Context<P::Input>) -> Result<Vec<P::Output>>
+where
+    P: Parser,
+    Sep: Parser<Input = P::Input>,
+{
+    let first = parent.parse(context)?;
+    let rest = PairRef {
+        left: sep,
+        right: parent,
+    };
+    repeat(
+        &rest,
+        context,
+        (0, usize::MAX),
+        vec![first],
+        |res, (_, val)| push(res, val),
+    )
+}
+
+/// SepBy
+///
+/// Like `Split`, but also matches no item at all.
+#[derive(Debug, Clone)]
+pub struct SepBy<Parent, Sep> {
+    pub(super) parent: Parent,
+    pub(super) sep: Sep,
+}
+
+impl<Parent, Sep> Parser for SepBy<Parent, Sep>
+where
+    Parent: Parser,
+    Sep: Parser<Input = Parent::Input>,
+{
+    type Input = Parent::Input;
+    type Output = Vec<Parent::Output>;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        Ok(split(&self.parent, &self.sep, context).unwrap_or_default())
+    }
+}
+
+/// SepEndBy
+///
+/// Items separated and optionally ended by `sep`, such as `1, 2, 3,`.
+#[derive(Debug, Clone)]
+pub struct SepEndBy<Parent, Sep> {
+    pub(super) parent: Parent,
+    pub(super) sep: Sep,
+}
+
+impl<Parent, Sep> Parser for SepEndBy<Parent, Sep>
+where
+    Parent: Parser,
+    Sep: Parser<Input = Parent::Input>,
+{
+    type Input = Parent::Input;
+    type Output = Vec<Parent::Output>;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        let mut res = Vec::new();
+        loop {
+            let pos = context.pos();
+            let Ok(val) = self.parent.parse(context) else {
+                break;
+            };
+            res.push(val);
+            if self.sep.parse(context).is_err() || context.pos() == pos {
+                break;
+            }
+        }
         Ok(res)
     }
 }
 
+/// ChainL1
+///
+/// One or more items separated by operators, whose outputs combine the items
+/// from left to right: `1 - 2 - 3` is `(1 - 2) - 3`.
+#[derive(Debug, Clone)]
+pub struct ChainL1<Parent, Op> {
+    pub(super) parent: Parent,
+    pub(super) op: Op,
+}
+
+impl<Parent, Op> Parser for ChainL1<Parent, Op>
+where
+    Parent: Parser,
+    Op: Parser<Input = Parent::Input>,
+    Op::Output: Fn(Parent::Output, Parent::Output) -> Parent::Output,
+{
+    type Input = Parent::Input;
+    type Output = Parent::Output;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        let first = self.parent.parse(context)?;
+        let rest = PairRef {
+            left: &self.op,
+            right: &self.parent,
+        };
+        repeat(&rest, context, (0, usize::MAX), first, |lhs, (f, rhs)| {
+            f(lhs, rhs)
+        })
+    }
+}
+
+/// ChainR1
+///
+/// Like `ChainL1`, but combines the items from right to left: `2 ^ 3 ^ 2` is
+/// `2 ^ (3 ^ 2)`.
+#[derive(Debug, Clone)]
+pub struct ChainR1<Parent, Op> {
+    pub(super) parent: Parent,
+    pub(super) op: Op,
+}
+
+impl<Parent, Op> Parser for ChainR1<Parent, Op>
+where
+    Parent: Parser,
+    Op: Parser<Input = Parent::Input>,
+    Op::Output: Fn(Parent::Output, Parent::Output) -> Parent::Output,
+{
+    type Input = Parent::Input;
+    type Output = Parent::Output;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        let first = self.parent.parse(context)?;
+        let rest = PairRef {
+            left: &self.op,
+            right: &self.parent,
+        };
+        let mut rest = repeat(&rest, context, (0, usize::MAX), Vec::new(), push)?;
+        let Some((mut f, mut rhs)) = rest.pop() else {
+            return Ok(first);
+        };
+        while let Some((prev_f, lhs)) = rest.pop() {
+            rhs = f(lhs, rhs);
+            f = prev_f;
+        }
+        Ok(f(first, rhs))
+    }
+}
+
 /// Label
 #[derive(Debug, Clone)]
 pub struct Label<Parent, F> {
@@ -317,9 +618,10 @@ where
     Msg: Into<Cow<'static, str>>,
     F: Clone + Fn() -> Msg,
 {
+    type Input = Parent::Input;
     type Output = Parent::Output;
 
-    fn parse_raw(&self, context: &mut Context) -> Result<Self::Output> {
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
         self.parent.parse(context).map_err(|mut e| {
             e.msg = (self.f)().into();
             e
@@ -327,6 +629,274 @@ where
     }
 }
 
+/// Spanned
+#[derive(Debug, Clone)]
+pub struct Spanned<Parent> {
+    pub(super) parent: Parent,
+}
+
+impl<Parent> Parser for Spanned<Parent>
+where
+    Parent: Parser,
+{
+    type Input = Parent::Input;
+    type Output = (Parent::Output, Range<usize>);
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        let start = context.pos();
+        let val = self.parent.parse(context)?;
+        Ok((val, start..context.pos()))
+    }
+}
+
+/// Recognize
+#[derive(Debug, Clone)]
+pub struct Recognize<Parent> {
+    pub(super) parent: Parent,
+}
+
+impl<Parent> Parser for Recognize<Parent>
+where
+    Parent: Parser<Input: Sliceable>,
+{
+    type Input = Parent::Input;
+    type Output = <Parent::Input as Sliceable>::Slice;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        let checkpoint = context.checkpoint();
+        self.parent.parse(context)?;
+        Ok(context.stream().slice_since(&checkpoint))
+    }
+}
+
+/// LookAhead
+///
+/// Runs the parent without consuming any input.
+#[derive(Debug, Clone)]
+pub struct LookAhead<Parent> {
+    pub(super) parent: Parent,
+}
+
+impl<Parent> Parser for LookAhead<Parent>
+where
+    Parent: Parser,
+{
+    type Input = Parent::Input;
+    type Output = Parent::Output;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        let checkpoint = context.checkpoint();
+        let val = self.parent.parse(context)?;
+        context.reset(checkpoint);
+        Ok(val)
+    }
+}
+
+/// Peek
+///
+/// Like `LookAhead`, but never fails: the output is `None` where the parent
+/// fails, such as at the end of the stream.
+#[derive(Debug, Clone)]
+pub struct Peek<Parent> {
+    pub(super) parent: Parent,
+}
+
+impl<Parent> Parser for Peek<Parent>
+where
+    Parent: Parser,
+{
+    type Input = Parent::Input;
+    type Output = Option<Parent::Output>;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        let checkpoint = context.checkpoint();
+        let val = self.parent.parse(context).ok();
+        context.reset(checkpoint);
+        Ok(val)
+    }
+}
+
+/// NotFollowedBy
+///
+/// Fails where `next` matches right after the parent, e.g. a keyword
+/// followed by more identifier chars. `next` consumes nothing, and the end
+/// of the stream is never matched by it.
+#[derive(Debug, Clone)]
+pub struct NotFollowedBy<Parent, Next> {
+    pub(super) parent: Parent,
+    pub(super) next: Next,
+}
+
+impl<Parent, Next> Parser for NotFollowedBy<Parent, Next>
+where
+    Parent: Parser,
+    Next: Parser<Input = Parent::Input>,
+{
+    type Input = Parent::Input;
+    type Output = Parent::Output;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        let val = self.parent.parse(context)?;
+        let checkpoint = context.checkpoint();
+        if self.next.parse(context).is_ok() {
+            context.reset(checkpoint);
+            return Err(context.throw_parser_err("unexpected input"));
+        }
+        Ok(val)
+    }
+}
+
+/// Memo
+///
+/// Packrat memoization: the result and end position of the parent at every
+/// position are stored in the `Context`, so it runs at most once per
+/// position however often the grammar backtracks over it. The errors it
+/// reports are stored too, and reported again when the result is reused.
+/// Under a `Stateful` stream, a result is reused only if the state is the
+/// same as when it was parsed. Clones share the memo entries of the parser
+/// they were cloned from.
+#[derive(Debug, Clone)]
+pub struct Memo<Parent> {
+    pub(super) parent: Parent,
+    pub(super) id: usize,
+}
+
+impl<Parent> Memo<Parent> {
+    pub(super) fn new(parent: Parent) -> Self {
+        Self {
+            parent,
+            id: next_memo_id(),
+        }
+    }
+}
+
+impl<Parent> Parser for Memo<Parent>
+where
+    Parent: Parser<Input: Stream<Checkpoint: 'static>, Output: Clone + 'static>,
+{
+    type Input = Parent::Input;
+    type Output = Parent::Output;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        // Start and end checkpoints, result and errors reported
+        type Entry<S, O> = (
+            <S as Stream>::Checkpoint,
+            <S as Stream>::Checkpoint,
+            Result<O>,
+            Vec<super::Error>,
+        );
+
+        let key = (self.id, context.pos());
+        if let Some((start, end, res, errors)) =
+            context.memoized::<Entry<Self::Input, Self::Output>>(key)
+        {
+            if context.stream().same_state(start) {
+                let (end, res, errors) = (end.clone(), res.clone(), errors.clone());
+                context.reset(end);
+                errors.into_iter().for_each(|err| context.report(err));
+                return res;
+            }
+        }
+        let start = context.checkpoint();
+        let errors = context.errors().len();
+        let res = self.parent.parse(context);
+        let end = context.checkpoint();
+        let errors = context.errors()[errors..].to_vec();
+        let entry = (start, end, res.clone(), errors);
+        context.memoize::<Entry<Self::Input, Self::Output>>(key, entry);
+        res
+    }
+}
+
+/// RecoverWith
+///
+/// Where the parent fails, reports its error to the `Context` and runs
+/// `recovery` instead, which typically skips the bad input and outputs a
+/// placeholder. Fails with the error of the parent if `recovery` fails too.
+#[derive(Debug, Clone)]
+pub struct RecoverWith<Parent, Recovery> {
+    pub(super) parent: Parent,
+    pub(super) recovery: Recovery,
+}
+
+impl<Parent, Recovery> Parser for RecoverWith<Parent, Recovery>
+where
+    Parent: Parser,
+    Recovery: Parser<Input = Parent::Input, Output = Parent::Output>,
+{
+    type Input = Parent::Input;
+    type Output = Parent::Output;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        let err = match self.parent.parse(context) {
+            Ok(val) => return Ok(val),
+            Err(err) => err,
+        };
+        match self.recovery.parse(context) {
+            Ok(val) => {
+                context.report(err);
+                Ok(val)
+            }
+            Err(_) => Err(err),
+        }
+    }
+}
+
+/// OrDefaultOnError
+///
+/// Where the parent fails, reports its error to the `Context` and outputs
+/// the default value without consuming any input.
+#[derive(Debug, Clone)]
+pub struct OrDefaultOnError<Parent> {
+    pub(super) parent: Parent,
+}
+
+impl<Parent> Parser for OrDefaultOnError<Parent>
+where
+    Parent: Parser<Output: Default>,
+{
+    type Input = Parent::Input;
+    type Output = Parent::Output;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        self.parent.parse(context).or_else(|err| {
+            context.report(err);
+            Ok(Default::default())
+        })
+    }
+}
+
+/// Named
+///
+/// Records its runs in the trace of the `Context`, if tracing is enabled.
+#[derive(Debug, Clone)]
+pub struct Named<Parent> {
+    pub(super) parent: Parent,
+    pub(super) name: Cow<'static, str>,
+}
+
+impl<Parent> Parser for Named<Parent>
+where
+    Parent: Parser,
+{
+    type Input = Parent::Input;
+    type Output = Parent::Output;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        let start = context.pos();
+        let Some(trace) = context.trace_mut() else {
+            return self.parent.parse(context);
+        };
+        trace.enter(self.name.clone(), start);
+        let res = self.parent.parse(context);
+        let end = context.pos();
+        if let Some(trace) = context.trace_mut() {
+            trace.exit(end, res.as_ref().err().cloned());
+        }
+        res
+    }
+}
+
 /// Debug
 #[derive(Debug, Clone)]
 pub struct Debug<Parent, F> {
@@ -339,11 +909,257 @@ where
     Parent: Parser<Output: std::fmt::Debug>,
     F: Clone + Fn(&Result<Parent::Output>),
 {
+    type Input = Parent::Input;
     type Output = Parent::Output;
 
-    fn parse_raw(&self, context: &mut Context) -> Result<Self::Output> {
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
         let res = self.parent.parse(context);
         (self.f)(&res);
         res
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use crate::{consumers::*, just, Context, Parser};
+
+    #[test]
+    fn memo_replays_errors() {
+        // A number, or `?` in its place with an error.
+        let num = number().recover_with(char('?').map(|_| 0)).memo();
+        // The first alternative reports, then backtracks and drops the
+        // error, the second reuses the memoized result.
+        let parser = num.clone().pair(char('!')).map(|(n, _)| n).or(num);
+        let mut context = Context::from_str("?");
+        assert_eq!(context.parse_by(parser).unwrap(), 0);
+        let errors: Vec<_> = context.errors().iter().map(|err| err.pos).collect();
+        assert_eq!(errors, [0]);
+    }
+
+    #[test]
+    fn many_till_stops_without_progress() {
+        let parser = just(|| 'x').many_till(char(';'));
+        let err = Context::from_str("a;").parse_by(parser).unwrap_err();
+        assert_eq!(err.pos, 0);
+        let parser = letter().or(just(|| ' ')).many_till(char(';'));
+        assert_eq!(
+            Context::from_str("ab;").parse_by(parser.clone()).unwrap(),
+            ['a', 'b']
+        );
+        assert!(Context::from_str("ab1;").parse_by(parser).is_err());
+    }
+
+    #[test]
+    fn skip_many() {
+        let parser = char(' ').skip_many().use_right(letter());
+        assert_eq!(Context::from_str("   a").parse_by(parser).unwrap(), 'a');
+    }
+
+    // The output and the position after it.
+    fn parse<'a, P>(parser: P, input: &'a str) -> (P::Output, usize)
+    where
+        P: Parser<Input = crate::StrStream<'a>>,
+    {
+        let mut context = Context::from_str(input);
+        let val = context.parse_by(parser).unwrap();
+        (val, context.pos())
+    }
+
+    #[test]
+    fn optional() {
+        let sign = char('-').optional();
+        assert_eq!(
+            parse(sign.clone().pair(number()), "-1"),
+            ((Some('-'), 1), 2)
+        );
+        assert_eq!(parse(sign.pair(number()), "1"), ((None, 1), 1));
+        // A failed match consumes nothing.
+        let arrow = string("->").optional().pair(any_char());
+        assert_eq!(parse(arrow, "-x"), ((None, '-'), 1));
+    }
+
+    #[test]
+    fn count_and_many_m_n() {
+        assert_eq!(parse(digit().count(2), "123"), (vec!['1', '2'], 2));
+        let err = Context::from_str("1a")
+            .parse_by(digit().count(2))
+            .unwrap_err();
+        assert_eq!(err.pos, 1);
+        assert_eq!(parse(digit().count(0), "1"), (vec![], 0));
+
+        let digits = digit().many_m_n(2, 3);
+        assert_eq!(parse(digits.clone(), "1234"), (vec!['1', '2', '3'], 3));
+        assert_eq!(parse(digits.clone(), "12a"), (vec!['1', '2'], 2));
+        assert!(Context::from_str("1a").parse_by(digits).is_err());
+
+        // Empty matches count towards `min` only.
+        assert_eq!(parse(just(|| 0).count(3), "1"), (vec![0, 0, 0], 0));
+        assert_eq!(parse(just(|| 0).many_m_n(1, 3), "1"), (vec![0], 0));
+        assert_eq!(parse(just(|| 0).many(), "1"), (vec![], 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "min 3 is greater than max 2")]
+    fn many_m_n_bounds() {
+        let _ = digit::<crate::StrStream>().many_m_n(3, 2);
+    }
+
+    #[test]
+    fn many_undoes_empty_matches() {
+        // The empty match reports an error, dropped with it.
+        let item = digit().recover_with(just(|| '0'));
+        let mut context = Context::from_str("12a");
+        assert_eq!(context.parse_by(item.many()).unwrap(), ['1', '2']);
+        assert!(context.errors().is_empty());
+        assert_eq!(context.pos(), 2);
+    }
+
+    #[test]
+    fn separators() {
+        let input = "1,2,3,;";
+        let sep = || char(',');
+        assert_eq!(parse(number().sep_by(sep()), input), (vec![1, 2, 3], 5));
+        assert_eq!(parse(number().sep_end_by(sep()), input), (vec![1, 2, 3], 6));
+        assert_eq!(parse(number().end_by(sep()), input), (vec![1, 2, 3], 6));
+        assert_eq!(parse(number().split(sep()), input), (vec![1, 2, 3], 5));
+
+        assert_eq!(parse(number().sep_by(sep()), ";"), (vec![], 0));
+        assert_eq!(parse(number().sep_end_by(sep()), ";"), (vec![], 0));
+        assert!(Context::from_str(";")
+            .parse_by(number().split(sep()))
+            .is_err());
+        // Every item is ended, the last one too.
+        assert_eq!(parse(number().end_by(sep()), "1,2"), (vec![1], 2));
+    }
+
+    #[test]
+    fn fold_many() {
+        let sum = digit()
+            .map(|c| c.to_digit(10).unwrap())
+            .fold_many(|| 0, |acc, n| acc + n);
+        assert_eq!(parse(sum.clone(), "123a"), (6, 3));
+        assert_eq!(parse(sum, "a"), (0, 0));
+    }
+
+    #[test]
+    fn chain_associativity() {
+        let op = || {
+            one_of("-^").map(|op| move |lhs: String, rhs: String| format!("({}{}{})", lhs, op, rhs))
+        };
+        let item = || digit().map(String::from);
+        assert_eq!(
+            parse(item().chainl1(op()), "1-2-3"),
+            ("((1-2)-3)".into(), 5)
+        );
+        assert_eq!(
+            parse(item().chainr1(op()), "1^2^3"),
+            ("(1^(2^3))".into(), 5)
+        );
+        assert_eq!(parse(item().chainr1(op()), "1"), ("1".into(), 1));
+        // A trailing operator is left over.
+        assert_eq!(parse(item().chainl1(op()), "1-2-"), ("(1-2)".into(), 3));
+        assert_eq!(parse(item().chainr1(op()), "1^2^"), ("(1^2)".into(), 3));
+    }
+
+    #[test]
+    fn recognize() {
+        let float = || {
+            digit()
+                .many()
+                .pair(char('.'))
+                .pair(digit().many())
+                .recognize()
+        };
+        assert_eq!(parse(float(), "12.5x"), ("12.5", 4));
+        assert_eq!(parse(float(), ".x"), (".", 1));
+        let err = Context::from_str("12x").parse_by(float()).unwrap_err();
+        assert_eq!(err.pos, 2);
+        let mut context = Context::from_slice(&[1, 2, 3]);
+        let items = any().pair(any()).recognize();
+        assert_eq!(context.parse_by(items).unwrap(), [1, 2]);
+    }
+
+    #[test]
+    fn look_ahead_consumes_nothing() {
+        let ab = || char('a').pair(char('b'));
+        assert_eq!(parse(ab().look_ahead(), "abc"), (('a', 'b'), 0));
+        assert_eq!(
+            parse(letter().look_ahead().pair(any_char()), "x"),
+            (('x', 'x'), 1)
+        );
+        // Fails with the error of the parent.
+        let mut context = Context::from_str("ac");
+        let err = context.parse_by(ab().look_ahead()).unwrap_err();
+        assert_eq!((err.pos, context.pos()), (1, 0));
+    }
+
+    #[test]
+    fn peek_never_fails() {
+        let ab = || char('a').pair(char('b'));
+        assert_eq!(parse(ab().peek(), "abc"), (Some(('a', 'b')), 0));
+        assert_eq!(parse(ab().peek(), "ac"), (None, 0));
+        assert_eq!(parse(any_char().peek(), ""), (None, 0));
+    }
+
+    #[test]
+    fn not_followed_by() {
+        let keyword = || tag("if").not_followed_by(alphanumeric());
+        assert_eq!(parse(keyword(), "if x"), ("if", 2));
+        // The end of the stream is not matched.
+        assert_eq!(parse(keyword(), "if"), ("if", 2));
+        // `next` consumes nothing.
+        assert_eq!(parse(keyword().pair(any_char()), "if("), (("if", '('), 3));
+        // At the end of the parent, where `next` matched.
+        let mut context = Context::from_str("iffy");
+        let err = context.parse_by(keyword()).unwrap_err();
+        assert_eq!(
+            (err.pos, &*err.msg, context.pos()),
+            (2, "unexpected input", 0)
+        );
+        assert_eq!(parse(keyword().or(identifier()), "iffy"), ("iffy", 4));
+        // The error of the parent where it fails.
+        let err = Context::from_str("x").parse_by(keyword()).unwrap_err();
+        assert_eq!((err.pos, &*err.msg), (0, "expect \"if\""));
+    }
+
+    // Counts its clones.
+    #[derive(Debug, Default)]
+    struct Counted(Rc<Cell<usize>>);
+
+    impl Clone for Counted {
+        fn clone(&self) -> Self {
+            self.0.set(self.0.get() + 1);
+            Self(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn sequences_parse_in_place() {
+        let counted = Counted::default();
+        let clones = counted.0.clone();
+        let op = counted.clone();
+        let letter = satisfy(
+            move |c| {
+                let _ = &counted;
+                c.is_alphabetic()
+            },
+            "expect letter",
+        );
+        let item = char('<').use_right(letter).use_left(char('>'));
+        let list = item.split(char(',')).sep_by(char(';'));
+        let list = list.between(char('['), char(']'));
+        let sum = number().chainl1(char('+').map(move |_| {
+            let _ = &op;
+            |lhs, rhs| lhs + rhs
+        }));
+        clones.set(0);
+        assert_eq!(
+            parse(list, "[<a>,<b>;<c>]"),
+            (vec![vec!['a', 'b'], vec!['c']], 13)
+        );
+        assert_eq!(parse(sum, "1+2+3"), (6, 5));
+        assert_eq!(clones.get(), 0);
+    }
+}
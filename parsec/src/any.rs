@@ -1,15 +1,22 @@
-use std::rc::Rc;
+use std::{rc::Rc, sync::Arc};
 
-use super::{Context, Parser, Result};
+use super::{Context, Parser, Result, Stream};
 
-pub struct Any<Output> {
-    parse: Rc<dyn Fn(&mut Context) -> Result<Output>>,
+// The boxed `parse` of a parser.
+type ParseFn<'a, S, Output> = dyn Fn(&mut Context<S>) -> Result<Output> + 'a;
+type SyncParseFn<'a, S, Output> = dyn Fn(&mut Context<S>) -> Result<Output> + Send + Sync + 'a;
+
+pub struct Any<'a, S, Output> {
+    parse: Rc<ParseFn<'a, S, Output>>,
 }
 
-impl<Output> Any<Output> {
+impl<'a, S, Output> Any<'a, S, Output>
+where
+    S: Stream,
+{
     pub fn new<P>(parser: P) -> Self
     where
-        P: 'static + Parser<Output = Output>,
+        P: 'a + Parser<Input = S, Output = Output>,
     {
         Self {
             parse: Rc::new(move |ctx| parser.parse(ctx)),
@@ -17,7 +24,47 @@ impl<Output> Any<Output> {
     }
 }
 
-impl<Output> Clone for Any<Output> {
+impl<'a, S, Output> Clone for Any<'a, S, Output> {
+    fn clone(&self) -> Self {
+        Self {
+            parse: self.parse.clone(),
+        }
+    }
+}
+
+impl<'a, S, Output> Parser for Any<'a, S, Output>
+where
+    S: Stream,
+{
+    type Input = S;
+    type Output = Output;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        (self.parse)(context)
+    }
+}
+
+/// Like `Any`, but `Send` and `Sync`, so a parser can be built once and
+/// shared between threads.
+pub struct SyncAny<'a, S, Output> {
+    parse: Arc<SyncParseFn<'a, S, Output>>,
+}
+
+impl<'a, S, Output> SyncAny<'a, S, Output>
+where
+    S: Stream,
+{
+    pub fn new<P>(parser: P) -> Self
+    where
+        P: 'a + Send + Sync + Parser<Input = S, Output = Output>,
+    {
+        Self {
+            parse: Arc::new(move |ctx| parser.parse(ctx)),
+        }
+    }
+}
+
+impl<'a, S, Output> Clone for SyncAny<'a, S, Output> {
     fn clone(&self) -> Self {
         Self {
             parse: self.parse.clone(),
@@ -25,10 +72,63 @@ impl<Output> Clone for Any<Output> {
     }
 }
 
-impl<Output> Parser for Any<Output> {
+impl<'a, S, Output> Parser for SyncAny<'a, S, Output>
+where
+    S: Stream,
+{
+    type Input = S;
     type Output = Output;
 
-    fn parse_raw(&self, context: &mut Context) -> Result<Self::Output> {
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
         (self.parse)(context)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::{
+        consumers::*, PartialStream, SharedStr, SharedStrStream, StrStream, StreamParser,
+        SyncRecursive,
+    };
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn send_and_sync() {
+        assert_send_sync::<SyncAny<'static, SharedStrStream, i64>>();
+        assert_send_sync::<SyncAny<'static, StrStream<'static>, i64>>();
+        assert_send_sync::<StreamParser<SyncAny<'static, PartialStream, i64>>>();
+        assert_send_sync::<SyncRecursive<'static, StrStream<'static>, i64>>();
+        assert_send_sync::<SharedStrStream>();
+        assert_send_sync::<SharedStr>();
+        assert_send_sync::<PartialStream>();
+        assert_send_sync::<StrStream<'static>>();
+    }
+
+    #[test]
+    fn shared_between_threads() {
+        let sum = number()
+            .use_left(whitespaces())
+            .many()
+            .map(|numbers: Vec<i64>| numbers.iter().sum::<i64>())
+            .use_left(eos())
+            .to_sync_any();
+        let sums: Vec<_> = thread::scope(|scope| {
+            let handles: Vec<_> = (1..=8)
+                .map(|n| {
+                    let sum = &sum;
+                    scope.spawn(move || {
+                        let input = (1..=n).map(|i| i.to_string()).collect::<Vec<_>>();
+                        let input = SharedStrStream::new(input.join(" "));
+                        Context::new(input).parse_by(sum.clone()).unwrap()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(sums, [1, 3, 6, 10, 15, 21, 28, 36]);
+    }
+}
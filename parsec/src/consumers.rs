@@ -1,32 +1,185 @@
-use super::{Context, Parser, Result};
+use std::{borrow::Cow, fmt::Debug, marker::PhantomData, ops::RangeInclusive};
 
-pub fn any_char() -> AnyChar {
+use super::{Context, Error, Parser, Result, Sliceable, Stream};
+
+pub fn any<S>() -> AnyItem<S> {
+    AnyItem::new()
+}
+
+pub fn item<S>(item: S::Item) -> impl Parser<Input = S, Output = S::Item>
+where
+    S: Stream<Item: Clone + PartialEq + Debug>,
+{
+    let label = format!("expect {:?}", item);
+    any()
+        .filter(move |i| item.eq(i))
+        .label(move || label.clone())
+}
+
+// Slice consumers borrow what they read from the input instead of
+// collecting it.
+
+pub fn take_while<S, F>(predicate: F) -> TakeWhile<S, F>
+where
+    F: Fn(&S::Item) -> bool,
+    S: Stream,
+{
+    TakeWhile::new(predicate, false)
+}
+
+pub fn take_while1<S, F>(predicate: F) -> TakeWhile<S, F>
+where
+    F: Fn(&S::Item) -> bool,
+    S: Stream,
+{
+    TakeWhile::new(predicate, true)
+}
+
+/// Skips items up to and including the next match of `end`, e.g. to resume
+/// after a bad list:
+///
+/// ```
+/// use parsec::{consumers::*, Context, Parser};
+///
+/// let list = char('(').use_right(number().split(char(' '))).use_left(char(')'));
+/// let list = list.recover_with(skip_until(char(')')).map(|_| Vec::new()));
+/// let mut context = Context::from_str("(1 2)(3 x)(4)");
+/// let lists = context.parse_by(list.many()).unwrap();
+/// assert_eq!(lists, [vec![1, 2], vec![], vec![4]]);
+/// assert_eq!(context.errors().len(), 1);
+/// ```
+pub fn skip_until<P>(end: P) -> SkipUntil<P> {
+    SkipUntil { end }
+}
+
+pub fn tag<S>(tag: &str) -> Tag<'_, S> {
+    Tag::new(tag)
+}
+
+/// The match of `pattern` right at the current position, with its capture
+/// groups.
+///
+/// ```
+/// use parsec::{consumers::*, Context, Parser};
+///
+/// let float = regex(r"(\d+)\.(\d+)?");
+/// let res = Context::from_str("3.14 ").parse_by(float).unwrap();
+/// assert_eq!(res.matched, "3.14");
+/// assert_eq!(res.groups, [Some("3"), Some("14")]);
+/// ```
+///
+/// Panics if `pattern` is not a valid regex.
+#[cfg(feature = "regex")]
+pub fn regex<S>(pattern: &str) -> Regex<S> {
+    Regex::new(pattern)
+}
+
+// Char consumers work on any stream of items convertible to chars, such as
+// `&str` or `&[u8]`.
+
+pub fn any_char<S>() -> AnyChar<S> {
     AnyChar::new()
 }
 
-pub fn char(char: char) -> impl Parser<Output = char> {
-    any_char()
-        .filter(move |c| char.eq(c))
-        .label(move || format!("expect \'{}\'", char))
+/// A char matching `predicate`, failing with `label` at the char otherwise.
+pub fn satisfy<S, F>(predicate: F, label: impl Into<Cow<'static, str>>) -> Satisfy<S, F>
+where
+    F: Fn(char) -> bool,
+{
+    Satisfy::new(predicate, label.into())
+}
+
+pub fn char<S>(char: char) -> impl Parser<Input = S, Output = char>
+where
+    S: Stream<Item: Into<char>>,
+{
+    satisfy(move |c| c == char, format!("expect {:?}", char))
+}
+
+pub fn one_of<S>(chars: &str) -> impl Parser<Input = S, Output = char>
+where
+    S: Stream<Item: Into<char>>,
+{
+    let label = format!("expect one of {:?}", chars);
+    let chars = chars.to_owned();
+    satisfy(move |c| chars.contains(c), label)
+}
+
+pub fn none_of<S>(chars: &str) -> impl Parser<Input = S, Output = char>
+where
+    S: Stream<Item: Into<char>>,
+{
+    let label = format!("expect none of {:?}", chars);
+    let chars = chars.to_owned();
+    satisfy(move |c| !chars.contains(c), label)
+}
+
+pub fn char_range<S>(range: RangeInclusive<char>) -> impl Parser<Input = S, Output = char>
+where
+    S: Stream<Item: Into<char>>,
+{
+    let label = format!("expect {:?}..={:?}", range.start(), range.end());
+    satisfy(move |c| range.contains(&c), label)
+}
+
+pub fn whitespace<S>() -> impl Parser<Input = S, Output = char>
+where
+    S: Stream<Item: Into<char>>,
+{
+    satisfy(char::is_whitespace, "expect whitespace")
+}
+
+/// The whitespaces at the current position, borrowed from the input.
+pub fn whitespaces<S>() -> impl Parser<Input = S, Output = S::Slice>
+where
+    S: Sliceable<Item: Into<char>>,
+{
+    whitespace().skip_many().recognize()
+}
+
+pub fn alphabetic<S>() -> impl Parser<Input = S, Output = char>
+where
+    S: Stream<Item: Into<char>>,
+{
+    satisfy(|c| c.is_ascii_alphabetic(), "expect alphabetic")
 }
 
-pub fn whitespace() -> impl Parser<Output = char> {
-    any_char()
-        .filter(|c| c.is_whitespace())
-        .label(|| "expect whitespace")
+// Unicode-aware counterparts of the ASCII classes.
+
+pub fn letter<S>() -> impl Parser<Input = S, Output = char>
+where
+    S: Stream<Item: Into<char>>,
+{
+    satisfy(char::is_alphabetic, "expect letter")
 }
 
-pub fn whitespaces() -> impl Parser<Output = String> {
-    whitespace().many().map(|str| str.into_iter().collect())
+pub fn alphanumeric<S>() -> impl Parser<Input = S, Output = char>
+where
+    S: Stream<Item: Into<char>>,
+{
+    satisfy(char::is_alphanumeric, "expect alphanumeric")
 }
 
-pub fn alphabetic() -> impl Parser<Output = char> {
-    any_char()
-        .filter(|c| c.is_ascii_alphabetic())
-        .label(|| "expect alphabetic")
+/// A char that can start an identifier, per Unicode `XID_Start`.
+pub fn xid_start<S>() -> impl Parser<Input = S, Output = char>
+where
+    S: Stream<Item: Into<char>>,
+{
+    satisfy(unicode_ident::is_xid_start, "expect identifier start")
 }
 
-pub fn number() -> impl Parser<Output = i64> {
+/// A char that can continue an identifier, per Unicode `XID_Continue`.
+pub fn xid_continue<S>() -> impl Parser<Input = S, Output = char>
+where
+    S: Stream<Item: Into<char>>,
+{
+    satisfy(unicode_ident::is_xid_continue, "expect identifier char")
+}
+
+pub fn number<S>() -> impl Parser<Input = S, Output = i64>
+where
+    S: Stream<Item: Into<char>>,
+{
     digit()
         .many()
         .map(|str| str.into_iter().collect::<String>())
@@ -34,90 +187,249 @@ pub fn number() -> impl Parser<Output = i64> {
         .label(|| "expect number")
 }
 
-pub fn digit() -> impl Parser<Output = char> {
-    any_char()
-        .filter(|c| c.is_ascii_digit())
-        .label(|| "expect digit")
+pub fn digit<S>() -> impl Parser<Input = S, Output = char>
+where
+    S: Stream<Item: Into<char>>,
+{
+    satisfy(|c| c.is_ascii_digit(), "expect digit")
 }
 
-pub fn identifier() -> impl Parser<Output = String> {
+/// An ASCII identifier, borrowed from the input.
+pub fn identifier<S>() -> impl Parser<Input = S, Output = S::Slice>
+where
+    S: Sliceable<Item: Into<char>>,
+{
     alphabetic()
         .or(char('_'))
-        .pair(
-            alphabetic()
-                .or(digit())
-                .or(char('_'))
-                .many()
-                .map(|str| str.into_iter().collect::<String>()),
-        )
-        .map(|(first, mut str)| {
-            str.insert(0, first);
-            str
-        })
+        .pair(alphabetic().or(digit()).or(char('_')).skip_many())
+        .recognize()
         .label(|| "expect identifier")
 }
 
-pub fn string(str: &str) -> Str {
+pub fn string<S>(str: &str) -> Str<S> {
     Str::new(str)
 }
 
-pub fn bool() -> impl Parser<Output = bool> {
+/// Like `string`, but ignoring case. The output is the input as written.
+///
+/// Chars are lowercased and compared one by one, so `ß` matches `ẞ` but not
+/// `SS`.
+pub fn string_ci<S>(str: &str) -> StrCi<'_, S> {
+    StrCi::new(str)
+}
+
+pub fn bool<S>() -> impl Parser<Input = S, Output = bool>
+where
+    S: Stream<Item: Into<char>>,
+{
     string("true")
         .map(|_| true)
         .or(string("false").map(|_| false))
         .label(|| "expect bool")
 }
 
-pub fn eos() -> EOS {
+pub fn eos<S>() -> EOS<S> {
     EOS::new()
 }
 
 // ============
 
+/// AnyItem
+#[derive(Debug, Clone)]
+pub struct AnyItem<S> {
+    marker: PhantomData<fn() -> S>,
+}
+
+impl<S> AnyItem<S> {
+    fn new() -> Self {
+        Self {
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<S> Default for AnyItem<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Parser for AnyItem<S>
+where
+    S: Stream,
+{
+    type Input = S;
+    type Output = S::Item;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        context.next()
+    }
+}
+
+/// TakeWhile
+#[derive(Debug, Clone)]
+pub struct TakeWhile<S, F> {
+    f: F,
+    non_empty: bool,
+    marker: PhantomData<fn() -> S>,
+}
+
+impl<S, F> TakeWhile<S, F> {
+    fn new(f: F, non_empty: bool) -> Self {
+        Self {
+            f,
+            non_empty,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<S, F> Parser for TakeWhile<S, F>
+where
+    S: Sliceable,
+    F: Clone + Fn(&S::Item) -> bool,
+{
+    type Input = S;
+    type Output = S::Slice;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        let start = context.checkpoint();
+        let start_pos = context.pos();
+        loop {
+            let checkpoint = context.checkpoint();
+            match context.next() {
+                Ok(item) if (self.f)(&item) => {}
+                _ => {
+                    context.reset(checkpoint);
+                    break;
+                }
+            }
+        }
+        if self.non_empty && context.pos() == start_pos {
+            return Err(context.throw_parser_err("expect at least one matching item"));
+        }
+        Ok(context.stream().slice_since(&start))
+    }
+}
+
+/// SkipUntil
+#[derive(Debug, Clone)]
+pub struct SkipUntil<P> {
+    end: P,
+}
+
+impl<P> Parser for SkipUntil<P>
+where
+    P: Parser,
+{
+    type Input = P::Input;
+    type Output = P::Output;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        loop {
+            if let Ok(val) = self.end.parse(context) {
+                return Ok(val);
+            }
+            context.next()?;
+        }
+    }
+}
+
 /// AnyChar
 #[derive(Debug, Clone)]
-pub struct AnyChar;
+pub struct AnyChar<S> {
+    marker: PhantomData<fn() -> S>,
+}
 
-impl AnyChar {
+impl<S> AnyChar<S> {
     fn new() -> Self {
-        Self
+        Self {
+            marker: PhantomData,
+        }
     }
 }
 
-impl Default for AnyChar {
+impl<S> Default for AnyChar<S> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl Parser for AnyChar {
+impl<S> Parser for AnyChar<S>
+where
+    S: Stream<Item: Into<char>>,
+{
+    type Input = S;
     type Output = char;
 
-    fn parse_raw(&self, context: &mut Context) -> Result<Self::Output> {
-        context.next()
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        context.next().map(Into::into)
+    }
+}
+
+/// Satisfy
+#[derive(Debug, Clone)]
+pub struct Satisfy<S, F> {
+    f: F,
+    label: Cow<'static, str>,
+    marker: PhantomData<fn() -> S>,
+}
+
+impl<S, F> Satisfy<S, F> {
+    fn new(f: F, label: Cow<'static, str>) -> Self {
+        Self {
+            f,
+            label,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<S, F> Parser for Satisfy<S, F>
+where
+    S: Stream<Item: Into<char>>,
+    F: Clone + Fn(char) -> bool,
+{
+    type Input = S;
+    type Output = char;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        let pos = context.pos();
+        match context.next().map(Into::into) {
+            Ok(char) if (self.f)(char) => Ok(char),
+            _ => Err(Error::new(pos, self.label.clone())),
+        }
     }
 }
 
 /// Str
 #[derive(Debug, Clone)]
-pub struct Str<'a> {
+pub struct Str<'a, S> {
     pub str: &'a str,
+    marker: PhantomData<fn() -> S>,
 }
 
-impl<'a> Str<'a> {
+impl<'a, S> Str<'a, S> {
     fn new(str: &'a str) -> Self {
-        Self { str }
+        Self {
+            str,
+            marker: PhantomData,
+        }
     }
 }
 
-impl<'a> Parser for Str<'a> {
+impl<'a, S> Parser for Str<'a, S>
+where
+    S: Stream<Item: Into<char>>,
+{
+    type Input = S;
     type Output = String;
 
-    fn parse_raw(&self, context: &mut Context) -> Result<Self::Output> {
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
         context
             .begin_tran(|ctx| {
                 for ref char in self.str.chars() {
-                    if !ctx.next()?.eq(char) {
+                    if !ctx.next()?.into().eq(char) {
                         return Err(ctx.throw_parser_err(""));
                     }
                 }
@@ -127,26 +439,168 @@ impl<'a> Parser for Str<'a> {
     }
 }
 
+/// Tag
+#[derive(Debug, Clone)]
+pub struct Tag<'a, S> {
+    pub tag: &'a str,
+    marker: PhantomData<fn() -> S>,
+}
+
+impl<'a, S> Tag<'a, S> {
+    fn new(tag: &'a str) -> Self {
+        Self {
+            tag,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, S> Parser for Tag<'a, S>
+where
+    S: Sliceable<Item: Into<char>>,
+{
+    type Input = S;
+    type Output = S::Slice;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        let start = context.checkpoint();
+        let pos = context.pos();
+        for char in self.tag.chars() {
+            if !context.next().is_ok_and(|c| c.into() == char) {
+                return Err(Error::new(pos, format!("expect \"{}\"", self.tag)));
+            }
+        }
+        Ok(context.stream().slice_since(&start))
+    }
+}
+
+/// Regex
+#[cfg(feature = "regex")]
+#[derive(Debug, Clone)]
+pub struct Regex<S> {
+    regex: ::regex::Regex,
+    pattern: String,
+    marker: PhantomData<fn() -> S>,
+}
+
+#[cfg(feature = "regex")]
+impl<S> Regex<S> {
+    fn new(pattern: &str) -> Self {
+        // Anchored at the current position.
+        let regex = ::regex::Regex::new(&format!(r"\A(?:{})", pattern))
+            .unwrap_or_else(|err| panic!("invalid regex {:?}: {}", pattern, err));
+        Self {
+            regex,
+            pattern: pattern.to_owned(),
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Output of `regex`: the matched input, and the input matched by every
+/// capture group, if any.
+#[cfg(feature = "regex")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegexMatch<T> {
+    pub matched: T,
+    pub groups: Vec<Option<T>>,
+}
+
+#[cfg(feature = "regex")]
+impl<S> Parser for Regex<S>
+where
+    S: super::TextStream,
+{
+    type Input = S;
+    type Output = RegexMatch<S::Slice>;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        let Some(captures) = self.regex.captures(context.stream().as_str()) else {
+            return Err(context.throw_parser_err(format!("expect /{}/", self.pattern)));
+        };
+        // Byte ranges of the match and of every group.
+        let ranges: Vec<_> = captures.iter().map(|m| m.map(|m| m.range())).collect();
+        let slice = |start: usize, end: usize| {
+            let mut stream = context.stream().clone();
+            stream.advance(start);
+            let checkpoint = stream.checkpoint();
+            stream.advance(end - start);
+            (stream.slice_since(&checkpoint), stream)
+        };
+        let (matched, end) = slice(0, ranges[0].as_ref().map_or(0, |range| range.end));
+        let groups = ranges[1..]
+            .iter()
+            .map(|range| range.as_ref().map(|range| slice(range.start, range.end).0))
+            .collect();
+        context.reset(end.checkpoint());
+        Ok(RegexMatch { matched, groups })
+    }
+}
+
+/// StrCi
+#[derive(Debug, Clone)]
+pub struct StrCi<'a, S> {
+    pub str: &'a str,
+    marker: PhantomData<fn() -> S>,
+}
+
+impl<'a, S> StrCi<'a, S> {
+    fn new(str: &'a str) -> Self {
+        Self {
+            str,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, S> Parser for StrCi<'a, S>
+where
+    S: Stream<Item: Into<char>>,
+{
+    type Input = S;
+    type Output = String;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        let pos = context.pos();
+        let mut output = String::with_capacity(self.str.len());
+        for char in self.str.chars() {
+            match context.next().map(Into::into) {
+                Ok(c) if c.to_lowercase().eq(char.to_lowercase()) => output.push(c),
+                _ => return Err(Error::new(pos, format!("expect {:?}", self.str))),
+            }
+        }
+        Ok(output)
+    }
+}
+
 /// EOS
 #[derive(Debug, Clone)]
-pub struct EOS;
+pub struct EOS<S> {
+    marker: PhantomData<fn() -> S>,
+}
 
-impl EOS {
+impl<S> EOS<S> {
     fn new() -> Self {
-        Self
+        Self {
+            marker: PhantomData,
+        }
     }
 }
 
-impl Default for EOS {
+impl<S> Default for EOS<S> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl Parser for EOS {
+impl<S> Parser for EOS<S>
+where
+    S: Stream,
+{
+    type Input = S;
     type Output = ();
 
-    fn parse_raw(&self, context: &mut Context) -> Result<Self::Output> {
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
         if context.next().is_err() {
             Ok(())
         } else {
@@ -154,3 +608,162 @@ impl Parser for EOS {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StrStream;
+
+    // The result and the position after it.
+    fn parse<'a, P>(parser: P, input: &'a str) -> (Result<P::Output>, usize)
+    where
+        P: Parser<Input = StrStream<'a>>,
+    {
+        let mut context = Context::from_str(input);
+        let res = context.parse_by(parser);
+        (res, context.pos())
+    }
+
+    fn error<'a, P>(parser: P, input: &'a str) -> (usize, String)
+    where
+        P: Parser<Input = StrStream<'a>>,
+    {
+        let Err(err) = Context::from_str(input).parse_by(parser) else {
+            panic!("expect an error");
+        };
+        (err.pos, err.msg.into_owned())
+    }
+
+    #[test]
+    fn take_while_borrows() {
+        let digits = || take_while(|c: &char| c.is_ascii_digit());
+        assert_eq!(parse(digits(), "12a").0.unwrap(), "12");
+        assert_eq!(parse(digits(), "12a").1, 2);
+        assert_eq!(parse(digits(), "a").0.unwrap(), "");
+        // Positions are in chars.
+        let (res, pos) = parse(take_while(|c: &char| c.is_alphabetic()), "日本1");
+        assert_eq!((res.unwrap(), pos), ("日本", 2));
+        let mut context = Context::from_slice(b"12a");
+        let bytes = context.parse_by(take_while(|b: &u8| b.is_ascii_digit()));
+        assert_eq!(bytes.unwrap(), b"12");
+    }
+
+    #[test]
+    fn take_while1_errors() {
+        let digits = || take_while1(|c: &char| c.is_ascii_digit());
+        assert_eq!(parse(digits(), "12a").0.unwrap(), "12");
+        let expect = "expect at least one matching item".to_owned();
+        assert_eq!(error(digits(), "a"), (0, expect.clone()));
+        assert_eq!(error(char('x').use_right(digits()), "xy"), (1, expect));
+        assert_eq!(
+            error(digits(), ""),
+            (0, "expect at least one matching item".into())
+        );
+    }
+
+    #[test]
+    fn tag_borrows() {
+        let (res, pos) = parse(tag("let"), "let x");
+        assert_eq!((res.unwrap(), pos), ("let", 3));
+        let (res, pos) = parse(tag("日本"), "日本語");
+        assert_eq!((res.unwrap(), pos), ("日本", 2));
+        // At the start of the tag, not where it differs.
+        assert_eq!(error(tag("let"), "lex"), (0, "expect \"let\"".into()));
+        assert_eq!(
+            error(char(' ').use_right(tag("if")), " i"),
+            (1, "expect \"if\"".into())
+        );
+    }
+
+    #[test]
+    fn whitespaces_and_identifier_borrow() {
+        let (res, pos) = parse(whitespaces(), " \t\n\u{3000}x");
+        assert_eq!((res.unwrap(), pos), (" \t\n\u{3000}", 4));
+        assert_eq!(parse(whitespaces(), "x").0.unwrap(), "");
+        let (res, pos) = parse(identifier(), "_a1 b");
+        assert_eq!((res.unwrap(), pos), ("_a1", 3));
+        assert_eq!(error(identifier(), "1a"), (0, "expect identifier".into()));
+        let mut context = Context::from_slice(b"ab1 ");
+        assert_eq!(context.parse_by(identifier()).unwrap(), b"ab1");
+    }
+
+    #[test]
+    fn satisfy_and_sets() {
+        let upper = || satisfy(char::is_uppercase, "expect upper");
+        assert_eq!(parse(upper(), "Éa"), (Ok('É'), 1));
+        assert_eq!(error(upper(), "éa"), (0, "expect upper".into()));
+        assert_eq!(error(upper(), ""), (0, "expect upper".into()));
+        assert_eq!(
+            error(char('a').use_right(upper()), "ab"),
+            (1, "expect upper".into())
+        );
+
+        assert_eq!(parse(one_of("+-"), "-1"), (Ok('-'), 1));
+        assert_eq!(parse(one_of("αβ"), "β"), (Ok('β'), 1));
+        assert_eq!(error(one_of("+-"), "*"), (0, "expect one of \"+-\"".into()));
+        assert_eq!(parse(none_of("()"), "é"), (Ok('é'), 1));
+        assert_eq!(
+            error(none_of("()"), "("),
+            (0, "expect none of \"()\"".into())
+        );
+        assert_eq!(
+            error(none_of("()"), ""),
+            (0, "expect none of \"()\"".into())
+        );
+
+        assert_eq!(parse(char_range('a'..='f'), "c"), (Ok('c'), 1));
+        assert_eq!(parse(char_range('α'..='ω'), "λ"), (Ok('λ'), 1));
+        assert_eq!(
+            error(char_range('a'..='f'), "g"),
+            (0, "expect 'a'..='f'".into())
+        );
+
+        // Over bytes too.
+        let mut context = Context::from_slice(b"-1");
+        assert_eq!(context.parse_by(one_of("+-")).unwrap(), '-');
+    }
+
+    #[test]
+    fn unicode_classes() {
+        assert_eq!(parse(letter(), "é"), (Ok('é'), 1));
+        assert_eq!(parse(letter(), "日"), (Ok('日'), 1));
+        assert_eq!(error(letter(), "1"), (0, "expect letter".into()));
+        assert_eq!(error(alphabetic(), "é"), (0, "expect alphabetic".into()));
+
+        assert_eq!(parse(xid_start(), "é"), (Ok('é'), 1));
+        assert_eq!(
+            error(xid_start(), "_"),
+            (0, "expect identifier start".into())
+        );
+        assert_eq!(
+            error(xid_start(), "1"),
+            (0, "expect identifier start".into())
+        );
+        assert_eq!(parse(xid_continue(), "1"), (Ok('1'), 1));
+        assert_eq!(parse(xid_continue(), "_"), (Ok('_'), 1));
+        assert_eq!(
+            error(xid_continue(), "-"),
+            (0, "expect identifier char".into())
+        );
+        let ident = xid_start().pair(xid_continue().skip_many()).recognize();
+        assert_eq!(parse(ident, "héllo_1 x"), (Ok("héllo_1"), 7));
+    }
+
+    #[test]
+    fn string_ignoring_case() {
+        assert_eq!(parse(string_ci("let"), "LeT x"), (Ok("LeT".into()), 3));
+        assert_eq!(error(string_ci("let"), "lex"), (0, "expect \"let\"".into()));
+        assert_eq!(error(string_ci("let"), "le"), (0, "expect \"let\"".into()));
+        // Char by char: `ẞ` lowercases to `ß`, `SS` is two chars.
+        assert_eq!(
+            parse(string_ci("straße"), "STRAẞE"),
+            (Ok("STRAẞE".into()), 6)
+        );
+        assert_eq!(parse(string_ci("ß"), "ẞ"), (Ok("ẞ".into()), 1));
+        assert_eq!(error(string_ci("ß"), "SS"), (0, "expect \"ß\"".into()));
+        assert_eq!(
+            error(string_ci("straße"), "STRASSE"),
+            (0, "expect \"straße\"".into())
+        );
+    }
+}
@@ -1,48 +1,171 @@
-use std::{borrow::Cow, str::Chars};
+use std::{
+    any::Any,
+    borrow::Cow,
+    collections::HashMap,
+    rc::Rc,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
-use super::{Error, Parser, Result};
+use super::{trace::Trace, Error, Parser, Result, SliceStream, Stateful, StrStream, Stream};
+
+// Results of memoized parsers, keyed by parser id and start position.
+type MemoTable = HashMap<(usize, usize), Rc<dyn Any>>;
+
+// Id of a parser in the memo table.
+pub(super) fn next_memo_id() -> usize {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
 
 #[derive(Debug, Clone)]
-pub struct Context<'a> {
-    chars: Chars<'a>,
-    pos: usize,
+pub struct Context<S> {
+    stream: S,
+    memo: MemoTable,
+    // Errors recovered from so far
+    errors: Vec<Error>,
+    trace: Option<Trace>,
 }
 
-impl<'a> Context<'a> {
-    pub fn new(chars: Chars<'a>) -> Self {
-        Self { chars, pos: 0 }
+impl<S> Context<S>
+where
+    S: Stream,
+{
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            memo: MemoTable::new(),
+            errors: Vec::new(),
+            trace: None,
+        }
     }
 
-    pub fn from_str(str: &'a str) -> Self {
-        Self::new(str.chars())
+    pub fn parse_by<T>(&mut self, parser: impl Parser<Input = S, Output = T>) -> Result<T> {
+        parser.parse(self)
     }
 
-    pub fn parse_by<T>(&mut self, parser: impl Parser<Output = T>) -> Result<T> {
-        parser.parse(self)
+    pub fn pos(&self) -> usize {
+        self.stream.pos()
     }
 
-    pub fn next(&mut self) -> Result<char> {
-        if let Some(char) = self.chars.next() {
-            self.pos += 1;
-            Ok(char)
-        } else {
-            Err(Error::eos(self.pos))
-        }
+    pub fn stream(&self) -> &S {
+        &self.stream
+    }
+
+    pub fn checkpoint(&self) -> S::Checkpoint {
+        self.stream.checkpoint()
+    }
+
+    pub fn reset(&mut self, checkpoint: S::Checkpoint) {
+        self.stream.reset(checkpoint)
+    }
+
+    pub fn next(&mut self) -> Result<S::Item> {
+        self.stream.next().ok_or_else(|| Error::eos(self.pos()))
     }
 
     pub fn throw_parser_err(&self, msg: impl Into<Cow<'static, str>>) -> Error {
-        Error::new(self.pos, msg)
+        Error::new(self.pos(), msg)
+    }
+
+    /// Records an error the parse recovered from. It is dropped again if an
+    /// enclosing parser fails and backtracks over it.
+    pub fn report(&mut self, err: Error) {
+        self.errors.push(err);
+    }
+
+    /// Errors recovered from so far, in the order they were reported.
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+
+    pub fn take_errors(&mut self) -> Vec<Error> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Starts recording a `Trace` of the named parsers run from now on.
+    pub fn enable_tracing(&mut self) {
+        self.trace.get_or_insert_with(Trace::default);
+    }
+
+    pub fn trace(&self) -> Option<&Trace> {
+        self.trace.as_ref()
+    }
+
+    /// Takes the trace recorded so far, which also stops tracing.
+    pub fn take_trace(&mut self) -> Option<Trace> {
+        self.trace.take()
+    }
+
+    /// The trace being recorded, for parsers recording runs of their own,
+    /// see [`Trace::enter`].
+    pub fn trace_mut(&mut self) -> Option<&mut Trace> {
+        self.trace.as_mut()
+    }
+
+    pub(super) fn memoized<T>(&self, key: (usize, usize)) -> Option<&T>
+    where
+        T: 'static,
+    {
+        self.memo.get(&key)?.downcast_ref()
+    }
+
+    pub(super) fn memoize<T>(&mut self, key: (usize, usize), value: T)
+    where
+        T: 'static,
+    {
+        self.memo.insert(key, Rc::new(value));
+    }
+
+    // Drops the errors reported after the first `len`, for parsers running
+    // their parent again.
+    pub(super) fn truncate_errors(&mut self, len: usize) {
+        self.errors.truncate(len);
     }
 
     pub(super) fn begin_tran<T, O>(&mut self, tran: T) -> Result<O>
     where
         T: Fn(&mut Self) -> Result<O>,
     {
-        let rollback_ctx = self.clone();
+        let checkpoint = self.checkpoint();
+        let errors = self.errors.len();
         let res = tran(self);
         if res.is_err() {
-            *self = rollback_ctx;
+            self.stream.backtrack(checkpoint);
+            self.errors.truncate(errors);
         }
         res
     }
 }
+
+impl<'a> Context<StrStream<'a>> {
+    pub fn from_str(str: &'a str) -> Self {
+        Self::new(StrStream::new(str))
+    }
+}
+
+impl<'a, T> Context<SliceStream<'a, T>>
+where
+    T: Clone,
+{
+    pub fn from_slice(slice: &'a [T]) -> Self {
+        Self::new(SliceStream::new(slice))
+    }
+}
+
+impl<S, U> Context<Stateful<S, U>>
+where
+    S: Stream,
+    U: Clone,
+{
+    pub fn with_state(stream: impl Into<S>, state: U) -> Self {
+        Self::new(Stateful::new(stream.into(), state))
+    }
+
+    pub fn state(&self) -> &U {
+        self.stream.state()
+    }
+
+    pub fn state_mut(&mut self) -> &mut U {
+        self.stream.state_mut()
+    }
+}
@@ -0,0 +1,280 @@
+//! Operator-precedence expressions.
+//!
+//! [`expr`] builds a Pratt parser over a term parser. Every operator is a
+//! parser with a binding power, higher binding tighter, and a function
+//! building the node from the output of the operator parser and its operands.
+//! Operators of the same kind are tried in the order they were declared.
+//!
+//! ```
+//! use parsec::{consumers::*, expr::expr, Context, Parser};
+//!
+//! fn calc(input: &str) -> parsec::Result<i64> {
+//!     let op = |c| char(c).use_left(whitespaces());
+//!     let calc = expr(number().use_left(whitespaces()))
+//!         .prefix(op('-'), 3, |_, x| -x)
+//!         .postfix(op('!'), 4, |x, _| (1..=x).product())
+//!         .infix_left(op('+').or(op('-')), 1, |lhs, op, rhs| match op {
+//!             '+' => lhs + rhs,
+//!             _ => lhs - rhs,
+//!         })
+//!         .infix_left(op('*').or(op('/')), 2, |lhs, op, rhs| match op {
+//!             '*' => lhs * rhs,
+//!             _ => lhs / rhs,
+//!         })
+//!         .infix_right(op('^'), 3, |lhs, _, rhs| lhs.pow(rhs as u32));
+//!     Context::from_str(input).parse_by(calc.use_left(eos()))
+//! }
+//!
+//! assert_eq!(calc("1 + 2 * 3").unwrap(), 7);
+//! assert_eq!(calc("10 - 4 - 3").unwrap(), 3);
+//! assert_eq!(calc("64 / 4 / 2").unwrap(), 8);
+//! assert_eq!(calc("2 ^ 3 ^ 2").unwrap(), 512);
+//! assert_eq!(calc("-2 ^ 2").unwrap(), -4);
+//! assert_eq!(calc("2 * 3! - -1").unwrap(), 13);
+//! assert!(calc("1 +").is_err());
+//! ```
+
+use std::rc::Rc;
+
+use super::{Any, Context, Parser, Result, Stream};
+
+type Unary<'a, O> = Box<dyn FnOnce(O) -> O + 'a>;
+type Binary<'a, O> = Box<dyn FnOnce(O, O) -> O + 'a>;
+
+pub fn expr<'a, P>(term: P) -> Expr<'a, P::Input, P::Output>
+where
+    P: 'a + Parser,
+{
+    Expr {
+        term: term.to_any(),
+        operators: Rc::new(Operators {
+            prefix: Vec::new(),
+            infix: Vec::new(),
+            postfix: Vec::new(),
+        }),
+    }
+}
+
+/// Expr
+pub struct Expr<'a, S, O> {
+    term: Any<'a, S, O>,
+    operators: Rc<Operators<'a, S, O>>,
+}
+
+// Binding powers are doubled, so that an operand binds to its left or right
+// operator first when both have the same power.
+struct Operators<'a, S, O> {
+    // Operator and power of its operand
+    prefix: Vec<(Any<'a, S, Unary<'a, O>>, u32)>,
+    // Operator, left and right powers
+    infix: Vec<(Any<'a, S, Binary<'a, O>>, u32, u32)>,
+    // Operator and left power
+    postfix: Vec<(Any<'a, S, Unary<'a, O>>, u32)>,
+}
+
+impl<'a, S, O> Expr<'a, S, O>
+where
+    S: 'a + Stream,
+    O: 'a,
+{
+    pub fn prefix<Op, F>(mut self, op: Op, power: u8, f: F) -> Self
+    where
+        Op: 'a + Parser<Input = S>,
+        F: 'a + Clone + Fn(Op::Output, O) -> O,
+    {
+        let op = op.map(move |op| -> Unary<'a, O> {
+            let f = f.clone();
+            Box::new(move |x| f(op, x))
+        });
+        let power = 2 * power as u32 + 1;
+        self.operators_mut().prefix.push((op.to_any(), power));
+        self
+    }
+
+    pub fn postfix<Op, F>(mut self, op: Op, power: u8, f: F) -> Self
+    where
+        Op: 'a + Parser<Input = S>,
+        F: 'a + Clone + Fn(O, Op::Output) -> O,
+    {
+        let op = op.map(move |op| -> Unary<'a, O> {
+            let f = f.clone();
+            Box::new(move |x| f(x, op))
+        });
+        let power = 2 * power as u32;
+        self.operators_mut().postfix.push((op.to_any(), power));
+        self
+    }
+
+    pub fn infix_left<Op, F>(self, op: Op, power: u8, f: F) -> Self
+    where
+        Op: 'a + Parser<Input = S>,
+        F: 'a + Clone + Fn(O, Op::Output, O) -> O,
+    {
+        let power = 2 * power as u32;
+        self.infix(op, (power, power + 1), f)
+    }
+
+    pub fn infix_right<Op, F>(self, op: Op, power: u8, f: F) -> Self
+    where
+        Op: 'a + Parser<Input = S>,
+        F: 'a + Clone + Fn(O, Op::Output, O) -> O,
+    {
+        let power = 2 * power as u32;
+        self.infix(op, (power + 1, power), f)
+    }
+
+    fn infix<Op, F>(mut self, op: Op, (left, right): (u32, u32), f: F) -> Self
+    where
+        Op: 'a + Parser<Input = S>,
+        F: 'a + Clone + Fn(O, Op::Output, O) -> O,
+    {
+        let op = op.map(move |op| -> Binary<'a, O> {
+            let f = f.clone();
+            Box::new(move |lhs, rhs| f(lhs, op, rhs))
+        });
+        self.operators_mut().infix.push((op.to_any(), left, right));
+        self
+    }
+
+    fn operators_mut(&mut self) -> &mut Operators<'a, S, O> {
+        Rc::make_mut(&mut self.operators)
+    }
+}
+
+impl<'a, S, O> Expr<'a, S, O>
+where
+    S: Stream,
+{
+    // Parses an expression whose operators bind at least as tight as
+    // `min_power`.
+    fn parse_power(&self, context: &mut Context<S>, min_power: u32) -> Result<O> {
+        let prefix = self
+            .operators
+            .prefix
+            .iter()
+            .find_map(|(op, power)| Some((op.parse(context).ok()?, *power)));
+        let mut lhs = match prefix {
+            Some((f, power)) => f(self.parse_power(context, power)?),
+            None => self.term.parse(context)?,
+        };
+        loop {
+            let checkpoint = context.checkpoint();
+            let postfix = self
+                .operators
+                .postfix
+                .iter()
+                .find_map(|(op, power)| Some((op.parse(context).ok()?, *power)));
+            if let Some((f, power)) = postfix {
+                if power < min_power {
+                    context.reset(checkpoint);
+                    break;
+                }
+                lhs = f(lhs);
+                continue;
+            }
+            let infix = self
+                .operators
+                .infix
+                .iter()
+                .find_map(|(op, left, right)| Some((op.parse(context).ok()?, *left, *right)));
+            if let Some((f, left, right)) = infix {
+                if left < min_power {
+                    context.reset(checkpoint);
+                    break;
+                }
+                let rhs = self.parse_power(context, right)?;
+                lhs = f(lhs, rhs);
+                continue;
+            }
+            break;
+        }
+        Ok(lhs)
+    }
+}
+
+impl<'a, S, O> Clone for Expr<'a, S, O> {
+    fn clone(&self) -> Self {
+        Self {
+            term: self.term.clone(),
+            operators: self.operators.clone(),
+        }
+    }
+}
+
+impl<'a, S, O> Clone for Operators<'a, S, O> {
+    fn clone(&self) -> Self {
+        Self {
+            prefix: self.prefix.clone(),
+            infix: self.infix.clone(),
+            postfix: self.postfix.clone(),
+        }
+    }
+}
+
+impl<'a, S, O> Parser for Expr<'a, S, O>
+where
+    S: Stream,
+{
+    type Input = S;
+    type Output = O;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        self.parse_power(context, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{consumers::*, recursive};
+
+    // The expression with every operation in parentheses.
+    fn show(input: &str) -> Result<String> {
+        let exp = recursive(|exp| {
+            let term = number()
+                .map(|n: i64| n.to_string())
+                .or(exp.between(char('('), char(')')));
+            expr(term)
+                .prefix(char('-'), 3, |_, x| format!("(-{})", x))
+                .postfix(char('!'), 4, |x, _| format!("({}!)", x))
+                .infix_left(one_of("+-"), 1, binary)
+                .infix_left(one_of("*/"), 2, binary)
+                .infix_right(char('^'), 3, binary)
+        });
+        Context::from_str(input).parse_by(exp.use_left(eos()))
+    }
+
+    fn binary(lhs: String, op: char, rhs: String) -> String {
+        format!("({}{}{})", lhs, op, rhs)
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        assert_eq!(show("1+2*3").unwrap(), "(1+(2*3))");
+        assert_eq!(show("1*2+3").unwrap(), "((1*2)+3)");
+        assert_eq!(show("1-2-3").unwrap(), "((1-2)-3)");
+        assert_eq!(show("8/4/2").unwrap(), "((8/4)/2)");
+        assert_eq!(show("2^3^2").unwrap(), "(2^(3^2))");
+        assert_eq!(show("2*3^2").unwrap(), "(2*(3^2))");
+    }
+
+    #[test]
+    fn prefix_postfix_and_parentheses() {
+        assert_eq!(show("-1*2").unwrap(), "((-1)*2)");
+        assert_eq!(show("-2^2").unwrap(), "(-(2^2))");
+        assert_eq!(show("--1").unwrap(), "(-(-1))");
+        assert_eq!(show("-3!").unwrap(), "(-(3!))");
+        assert_eq!(show("3!!").unwrap(), "((3!)!)");
+        assert_eq!(show("2*3!").unwrap(), "(2*(3!))");
+        assert_eq!(show("(1+2)*3").unwrap(), "((1+2)*3)");
+        assert_eq!(show("2^(1-1)!").unwrap(), "(2^((1-1)!))");
+        assert_eq!(show("-(1+2)").unwrap(), "(-(1+2))");
+    }
+
+    #[test]
+    fn error_positions() {
+        for (input, pos) in [("1+", 2), ("*2", 0), ("1*(2+", 5), ("(1", 2)] {
+            assert_eq!(show(input).unwrap_err().pos, pos, "{}", input);
+        }
+    }
+}
@@ -1,18 +1,18 @@
 use std::{borrow::Cow, marker::PhantomData};
 
-use super::{Parser, Result};
+use super::{Context, Parser, Result, Stream};
 
-pub fn fail<F, Output>(msg: F) -> Fail<F, Output> {
+pub fn fail<S, F, Output>(msg: F) -> Fail<S, F, Output> {
     Fail::new(msg)
 }
 
 #[derive(Debug)]
-pub struct Fail<F, Output> {
+pub struct Fail<S, F, Output> {
     f: F,
-    marker: PhantomData<Output>,
+    marker: PhantomData<fn() -> (S, Output)>,
 }
 
-impl<F, Output> Fail<F, Output> {
+impl<S, F, Output> Fail<S, F, Output> {
     pub fn new(msg: F) -> Self {
         Self {
             f: msg,
@@ -21,26 +21,28 @@ impl<F, Output> Fail<F, Output> {
     }
 }
 
-impl<F, Output> Clone for Fail<F, Output>
+impl<S, F, Output> Clone for Fail<S, F, Output>
 where
     F: Clone,
 {
     fn clone(&self) -> Self {
         Self {
             f: self.f.clone(),
-            marker: self.marker.clone(),
+            marker: self.marker,
         }
     }
 }
 
-impl<F, Output, Msg> Parser for Fail<F, Output>
+impl<S, F, Output, Msg> Parser for Fail<S, F, Output>
 where
+    S: Stream,
     Msg: Into<Cow<'static, str>>,
     F: Clone + Fn() -> Msg,
 {
+    type Input = S;
     type Output = Output;
 
-    fn parse_raw(&self, context: &mut crate::Context) -> Result<Self::Output> {
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
         Err(context.throw_parser_err((self.f)()))
     }
 }
@@ -1,6 +1,8 @@
-use super::{Context, Parser, Result};
+use std::marker::PhantomData;
 
-pub fn just<T, F>(output: F) -> Just<F>
+use super::{Context, Parser, Result, Stream};
+
+pub fn just<S, T, F>(output: F) -> Just<S, F>
 where
     F: Fn() -> T,
 {
@@ -8,23 +10,29 @@ where
 }
 
 #[derive(Debug, Clone)]
-pub struct Just<F> {
+pub struct Just<S, F> {
     f: F,
+    marker: PhantomData<fn() -> S>,
 }
 
-impl<F> Just<F> {
+impl<S, F> Just<S, F> {
     pub fn new(output: F) -> Self {
-        Self { f: output }
+        Self {
+            f: output,
+            marker: PhantomData,
+        }
     }
 }
 
-impl<T, F> Parser for Just<F>
+impl<S, T, F> Parser for Just<S, F>
 where
+    S: Stream,
     F: Clone + Fn() -> T,
 {
+    type Input = S;
     type Output = T;
 
-    fn parse_raw(&self, _context: &mut Context) -> Result<Self::Output> {
+    fn parse_raw(&self, _context: &mut Context<Self::Input>) -> Result<Self::Output> {
         Ok((self.f)())
     }
 }
@@ -0,0 +1,200 @@
+use super::{context::next_memo_id, recursive, Context, Parser, Recursive, Result, Stream};
+
+/// Builds a parser that may refer to itself, including in leftmost position,
+/// e.g. `expr := expr '-' term | term`.
+///
+/// `f` gets a handle to the parser being built. At every position the parser
+/// first fails where it refers to itself, then is rerun with its previous
+/// result as the seed as long as it consumes more input (seed growing, as in
+/// Warth et al.), which yields left-associative results. Rules in between
+/// may refer back to it, as long as they are not memoized themselves. Results
+/// are stored in the memo table of the `Context` like `memo()`: the errors
+/// reported are reported again where a result is reused, and under a
+/// `Stateful` stream a result is reused only if the state is the same.
+pub fn left_rec<'a, S, O, F, P>(f: F) -> Recursive<'a, S, O>
+where
+    S: 'a + Stream<Checkpoint: 'static>,
+    O: 'a + Clone + 'static,
+    F: FnOnce(Recursive<'a, S, O>) -> P,
+    P: 'a + Parser<Input = S, Output = O>,
+{
+    recursive(|this| LeftRec {
+        parent: f(this),
+        id: next_memo_id(),
+    })
+}
+
+/// LeftRec
+#[derive(Debug, Clone)]
+pub struct LeftRec<Parent> {
+    parent: Parent,
+    id: usize,
+}
+
+impl<Parent> Parser for LeftRec<Parent>
+where
+    Parent: Parser<Input: Stream<Checkpoint: 'static>, Output: Clone + 'static>,
+{
+    type Input = Parent::Input;
+    type Output = Parent::Output;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        // Start and end checkpoints, result and errors reported as in `Memo`,
+        // and whether the seed is still growing
+        type Entry<S, O> = (
+            <S as Stream>::Checkpoint,
+            <S as Stream>::Checkpoint,
+            Result<O>,
+            Vec<super::Error>,
+            bool,
+        );
+        // The longest result so far: result, end checkpoint and position, and
+        // errors reported
+        type Best<S, O> = (
+            Result<O>,
+            <S as Stream>::Checkpoint,
+            usize,
+            Vec<super::Error>,
+        );
+
+        let key = (self.id, context.pos());
+        if let Some((start, end, res, errors, growing)) =
+            context.memoized::<Entry<Self::Input, Self::Output>>(key)
+        {
+            // The call in leftmost position takes the seed whatever the
+            // state, or it would recurse forever.
+            if *growing || context.stream().same_state(start) {
+                let (end, res, errors) = (end.clone(), res.clone(), errors.clone());
+                context.reset(end);
+                errors.into_iter().for_each(|err| context.report(err));
+                return res;
+            }
+        }
+
+        let start = context.checkpoint();
+        let errors = context.errors().len();
+        let seed: Result<Self::Output> = Err(context.throw_parser_err("left recursion"));
+        let entry = (start.clone(), start.clone(), seed, Vec::new(), true);
+        context.memoize::<Entry<Self::Input, Self::Output>>(key, entry);
+        let mut best: Option<Best<Self::Input, Self::Output>> = None;
+        loop {
+            context.reset(start.clone());
+            context.truncate_errors(errors);
+            let res = self.parent.parse(context);
+            let pos = context.pos();
+            let grown = match &best {
+                None => true,
+                Some((Ok(_), _, best_pos, _)) => res.is_ok() && pos > *best_pos,
+                Some((Err(_), _, _, _)) => false,
+            };
+            if !grown {
+                break;
+            }
+            let end = context.checkpoint();
+            let reported = context.errors()[errors..].to_vec();
+            let entry = (
+                start.clone(),
+                end.clone(),
+                res.clone(),
+                reported.clone(),
+                true,
+            );
+            context.memoize::<Entry<Self::Input, Self::Output>>(key, entry);
+            best = Some((res, end, pos, reported));
+        }
+        let (res, end, _, reported) = best.unwrap();
+        context.reset(end.clone());
+        context.truncate_errors(errors);
+        reported.iter().for_each(|err| context.report(err.clone()));
+        let entry = (start, end, res.clone(), reported, false);
+        context.memoize::<Entry<Self::Input, Self::Output>>(key, entry);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{consumers::*, get_state, modify_state, Stateful, StrStream};
+
+    fn sub<'a>() -> Recursive<'a, StrStream<'a>, i64> {
+        left_rec(|expr| {
+            expr.use_left(char('-'))
+                .pair(number())
+                .map(|(lhs, rhs)| lhs - rhs)
+                .or(number())
+        })
+    }
+
+    #[test]
+    fn left_associative() {
+        let mut context = Context::from_str("10-4-3");
+        assert_eq!(context.parse_by(sub()).unwrap(), 3);
+        assert_eq!(context.pos(), 6);
+        assert_eq!(Context::from_str("7").parse_by(sub()).unwrap(), 7);
+    }
+
+    #[test]
+    fn indirect_recursion() {
+        // a := b 'a' | 'x'
+        // b := a 'b' | 'y'
+        let a = left_rec(|a| {
+            let b = a
+                .use_left(char('b'))
+                .map(|a| format!("({} b)", a))
+                .or(char('y').map(String::from));
+            b.use_left(char('a'))
+                .map(|b| format!("({} a)", b))
+                .or(char('x').map(String::from))
+        });
+        let mut context = Context::from_str("xbaba");
+        assert_eq!(context.parse_by(a.clone()).unwrap(), "((((x b) a) b) a)");
+        assert_eq!(context.pos(), 5);
+        let mut context = Context::from_str("ya");
+        assert_eq!(context.parse_by(a).unwrap(), "(y a)");
+    }
+
+    #[test]
+    fn error_positions() {
+        assert_eq!(Context::from_str("-1").parse_by(sub()).unwrap_err().pos, 0);
+        // The longest prefix parsed, `-` left over.
+        let mut context = Context::from_str("10-4-");
+        assert_eq!(context.parse_by(sub()).unwrap(), 6);
+        assert_eq!(context.pos(), 4);
+    }
+
+    #[test]
+    fn memo_replays_errors() {
+        let expr = left_rec(|expr| {
+            let term = number().recover_with(any_char().map(|_| 0));
+            expr.use_left(char('-'))
+                .pair(term.clone())
+                .map(|(lhs, rhs)| lhs - rhs)
+                .or(term)
+        });
+        let parser = expr.clone().use_left(char('!')).or(expr);
+        let mut context = Context::from_str("10-x-3");
+        assert_eq!(context.parse_by(parser).unwrap(), 7);
+        let errors: Vec<_> = context.errors().iter().map(|err| err.pos).collect();
+        assert_eq!(errors, [3]);
+    }
+
+    #[test]
+    fn memo_depends_on_the_state() {
+        type Input<'a> = Stateful<StrStream<'a>, i64>;
+        // Sums the states at every `a`.
+        let expr = left_rec(|expr| {
+            let term = get_state::<StrStream, i64>().use_left(char('a'));
+            expr.use_left(char('+'))
+                .pair(term.clone())
+                .map(|(lhs, rhs)| lhs + rhs)
+                .or(term)
+        });
+        let parser = modify_state(|n: &mut i64| *n += 1)
+            .use_right(expr.clone())
+            .use_left(char('!'))
+            .or(expr);
+        let mut context: Context<Input> = Context::with_state("a+a", 0);
+        assert_eq!(context.parse_by(parser).unwrap(), 0);
+    }
+}
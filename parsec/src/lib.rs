@@ -1,5 +1,11 @@
 #![feature(associated_type_bounds)]
 
+mod stream;
+pub use stream::{
+    IterStream, PartialStream, SharedStr, SharedStrStream, SliceStream, Sliceable, StrStream,
+    Stream, TextStream,
+};
+
 mod context;
 pub use context::Context;
 
@@ -16,9 +22,25 @@ mod fail;
 pub use fail::{fail, Fail};
 
 mod any;
-pub use any::Any;
+pub use any::{Any, SyncAny};
+
+mod recursive;
+pub use recursive::{recursive, sync_recursive, Recursive, SyncRecursive};
+
+mod state;
+pub use state::{get_state, modify_state, put_state, update_state, Stateful, UpdateState};
+
+mod partial;
+pub use partial::{Status, StreamParser};
+
+mod left_rec;
+pub use left_rec::{left_rec, LeftRec};
 
 pub mod consumers;
 pub use consumers::*;
 
 pub mod adapters;
+
+pub mod expr;
+
+pub mod trace;
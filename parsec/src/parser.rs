@@ -1,21 +1,31 @@
-use super::{adapters::*, Any, Context, Result};
+use std::borrow::Cow;
+
+use super::{adapters::*, Any, Context, Result, Stream, SyncAny};
 
 pub trait Parser: Clone {
+    type Input: Stream;
     type Output;
 
-    fn parse_raw(&self, context: &mut Context) -> Result<Self::Output>;
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output>;
 
-    fn parse(&self, context: &mut Context) -> Result<Self::Output> {
+    fn parse(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
         context.begin_tran(|ctx| self.parse_raw(ctx))
     }
 
-    fn to_any(self) -> Any<Self::Output>
+    fn to_any<'a>(self) -> Any<'a, Self::Input, Self::Output>
     where
-        Self: 'static,
+        Self: 'a,
     {
         Any::new(self)
     }
 
+    fn to_sync_any<'a>(self) -> SyncAny<'a, Self::Input, Self::Output>
+    where
+        Self: 'a + Send + Sync,
+    {
+        SyncAny::new(self)
+    }
+
     fn map<F, O>(self, f: F) -> Map<Self, F>
     where
         Self: Sized,
@@ -86,6 +96,17 @@ pub trait Parser: Clone {
         Some { parent: self }
     }
 
+    /// Zero or more matches. A match of the empty input ends the repetition
+    /// instead of going on forever, and is not part of the output.
+    ///
+    /// ```
+    /// use parsec::{consumers::*, just, Context, Parser};
+    ///
+    /// let digits = digit().many();
+    /// assert_eq!(Context::from_str("12a").parse_by(digits).unwrap(), ['1', '2']);
+    /// let empty = just(|| 0).many();
+    /// assert!(Context::from_str("12a").parse_by(empty).unwrap().is_empty());
+    /// ```
     fn many(self) -> Many<Self>
     where
         Self: Sized,
@@ -93,6 +114,63 @@ pub trait Parser: Clone {
         Many { parent: self }
     }
 
+    fn count(self, n: usize) -> Repeat<Self>
+    where
+        Self: Sized,
+    {
+        self.many_m_n(n, n)
+    }
+
+    /// Between `min` and `max` matches. Matches of the empty input count
+    /// towards `min`, but not beyond it, as with `many`.
+    ///
+    /// # Panics
+    ///
+    /// If `min` is greater than `max`.
+    fn many_m_n(self, min: usize, max: usize) -> Repeat<Self>
+    where
+        Self: Sized,
+    {
+        assert!(
+            min <= max,
+            "many_m_n: min {} is greater than max {}",
+            min,
+            max
+        );
+        Repeat {
+            parent: self,
+            min,
+            max,
+        }
+    }
+
+    fn fold_many<Init, F, Acc>(self, init: Init, f: F) -> FoldMany<Self, Init, F>
+    where
+        Self: Sized,
+        Init: Fn() -> Acc,
+        F: Fn(Acc, Self::Output) -> Acc,
+    {
+        FoldMany {
+            parent: self,
+            init,
+            f,
+        }
+    }
+
+    fn skip_many(self) -> SkipMany<Self>
+    where
+        Self: Sized,
+    {
+        SkipMany { parent: self }
+    }
+
+    fn optional(self) -> Optional<Self>
+    where
+        Self: Sized,
+    {
+        Optional { parent: self }
+    }
+
     fn many_till<E>(self, end: E) -> ManyTill<Self, E> {
         ManyTill { parent: self, end }
     }
@@ -112,10 +190,113 @@ pub trait Parser: Clone {
         Split { parent: self, sep }
     }
 
+    fn sep_by<Sep>(self, sep: Sep) -> SepBy<Self, Sep> {
+        SepBy { parent: self, sep }
+    }
+
+    fn sep_end_by<Sep>(self, sep: Sep) -> SepEndBy<Self, Sep> {
+        SepEndBy { parent: self, sep }
+    }
+
+    fn end_by<Sep>(self, sep: Sep) -> Many<UseLeft<Self, Sep>>
+    where
+        Self: Sized,
+        Sep: Parser<Input = Self::Input>,
+    {
+        self.use_left(sep).many()
+    }
+
+    /// ```
+    /// use parsec::{consumers::*, Context, Parser};
+    ///
+    /// let sub = char('-').map(|_| |lhs: i64, rhs: i64| lhs - rhs);
+    /// let pow = char('^').map(|_| |lhs: i64, rhs: i64| lhs.pow(rhs as u32));
+    /// let sub = number().chainl1(sub);
+    /// let pow = number().chainr1(pow);
+    /// assert_eq!(Context::from_str("10-4-3").parse_by(sub).unwrap(), 3);
+    /// assert_eq!(Context::from_str("2^3^2").parse_by(pow).unwrap(), 512);
+    /// ```
+    fn chainl1<Op>(self, op: Op) -> ChainL1<Self, Op> {
+        ChainL1 { parent: self, op }
+    }
+
+    fn chainr1<Op>(self, op: Op) -> ChainR1<Self, Op> {
+        ChainR1 { parent: self, op }
+    }
+
     fn label<F>(self, f: F) -> Label<Self, F> {
         Label { parent: self, f }
     }
 
+    fn spanned(self) -> Spanned<Self>
+    where
+        Self: Sized,
+    {
+        Spanned { parent: self }
+    }
+
+    fn recognize(self) -> Recognize<Self>
+    where
+        Self: Sized,
+    {
+        Recognize { parent: self }
+    }
+
+    fn look_ahead(self) -> LookAhead<Self>
+    where
+        Self: Sized,
+    {
+        LookAhead { parent: self }
+    }
+
+    fn peek(self) -> Peek<Self>
+    where
+        Self: Sized,
+    {
+        Peek { parent: self }
+    }
+
+    fn not_followed_by<P>(self, next: P) -> NotFollowedBy<Self, P>
+    where
+        Self: Sized,
+    {
+        NotFollowedBy { parent: self, next }
+    }
+
+    fn memo(self) -> Memo<Self>
+    where
+        Self: Sized,
+    {
+        Memo::new(self)
+    }
+
+    fn recover_with<R>(self, recovery: R) -> RecoverWith<Self, R>
+    where
+        Self: Sized,
+    {
+        RecoverWith {
+            parent: self,
+            recovery,
+        }
+    }
+
+    fn or_default_on_error(self) -> OrDefaultOnError<Self>
+    where
+        Self: Sized,
+    {
+        OrDefaultOnError { parent: self }
+    }
+
+    fn named(self, name: impl Into<Cow<'static, str>>) -> Named<Self>
+    where
+        Self: Sized,
+    {
+        Named {
+            parent: self,
+            name: name.into(),
+        }
+    }
+
     fn debug<F>(self, parse: F) -> Debug<Self, F>
     where
         F: Fn(&Result<Self::Output>),
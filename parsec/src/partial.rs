@@ -0,0 +1,274 @@
+//! Parsing input as it arrives.
+//!
+//! A [`StreamParser`] buffers the chunks fed to it, from a `&str` or an
+//! [`io::Read`], and runs its parser over what is buffered. Where the parse
+//! depends on the end of the buffer, read there on the way to the result or
+//! failing there, the outcome is [`Status::Incomplete`] rather than a result
+//! that more input could change. Once [`finish`] is called the end of the
+//! buffer is the end of the input.
+//!
+//! ```
+//! use parsec::{consumers::*, Parser, PartialStream, SharedStr, Status, StreamParser};
+//!
+//! fn word() -> impl Parser<Input = PartialStream, Output = SharedStr> {
+//!     whitespaces().use_right(identifier())
+//! }
+//!
+//! let mut words = StreamParser::new(word());
+//! words.feed("hello wor");
+//! assert!(matches!(words.parse_next(), Status::Parsed(word) if &*word == "hello"));
+//! // `wor` may go on.
+//! assert!(matches!(words.parse_next(), Status::Incomplete));
+//! words.feed("ld!");
+//! assert!(matches!(words.parse_next(), Status::Parsed(word) if &*word == "world"));
+//! assert!(matches!(words.parse_next(), Status::Failed(err) if err.pos == 11));
+//! ```
+//!
+//! Every run starts where the last successful one stopped, over the input
+//! buffered so far, which is only copied when more is fed. A unit cut across
+//! chunks is parsed again as each chunk arrives, so a parser should read
+//! small units, such as a line or a top-level form, rather than the whole
+//! input. `PartialStream` has no `TextStream` impl: a parser matching the
+//! remaining input as a `&str` could not tell whether it saw the end of it.
+//!
+//! [`finish`]: StreamParser::finish
+
+use std::{
+    io::{self, Read},
+    str,
+    sync::Arc,
+};
+
+use super::{Context, Error, Parser, PartialStream, Result};
+
+// Bytes read from an `io::Read` at a time
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Outcome of a `StreamParser` run.
+#[derive(Debug, Clone)]
+pub enum Status<O> {
+    Parsed(O),
+    /// The parser needs more input to tell.
+    Incomplete,
+    Failed(Error),
+}
+
+/// StreamParser
+///
+/// Runs a parser over input fed in chunks. Positions count the chars fed
+/// since the start, across chunks.
+#[derive(Debug, Clone)]
+pub struct StreamParser<P> {
+    parser: P,
+    // Input fed so far, shared with the stream of every run. The consumed
+    // part is dropped when more is fed.
+    buffer: Arc<str>,
+    // Byte offset of the input not consumed yet
+    offset: usize,
+    // Char position of that input
+    pos: usize,
+    // Bytes of a char split across chunks
+    partial_char: Vec<u8>,
+    finished: bool,
+    // Whether the last run was incomplete and nothing was fed since
+    waiting: bool,
+    errors: Vec<Error>,
+}
+
+impl<P> StreamParser<P>
+where
+    P: Parser<Input = PartialStream>,
+{
+    pub fn new(parser: P) -> Self {
+        Self {
+            parser,
+            buffer: Arc::from(""),
+            offset: 0,
+            pos: 0,
+            partial_char: Vec::new(),
+            finished: false,
+            waiting: false,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &str) {
+        let mut buffer = String::with_capacity(self.pending().len() + chunk.len());
+        buffer.push_str(self.pending());
+        buffer.push_str(chunk);
+        self.buffer = buffer.into();
+        self.offset = 0;
+        self.waiting = false;
+    }
+
+    /// Marks the end of the input.
+    pub fn finish(&mut self) {
+        self.finished = true;
+        self.waiting = false;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Feeds one chunk of UTF-8 read from `reader`, and finishes the input
+    /// when it is exhausted. Returns the number of bytes read.
+    pub fn read_from(&mut self, reader: &mut impl Read) -> io::Result<usize> {
+        let mut chunk = [0; CHUNK_SIZE];
+        let len = loop {
+            match reader.read(&mut chunk) {
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                res => break res?,
+            }
+        };
+        if len == 0 {
+            self.finish();
+            if !self.partial_char.is_empty() {
+                return Err(invalid_utf8());
+            }
+            return Ok(0);
+        }
+        let mut bytes = std::mem::take(&mut self.partial_char);
+        bytes.extend_from_slice(&chunk[..len]);
+        let (valid, res) = match str::from_utf8(&bytes) {
+            Ok(str) => (str, Ok(len)),
+            Err(err) => {
+                let valid = str::from_utf8(&bytes[..err.valid_up_to()]).unwrap();
+                match err.error_len() {
+                    // A char cut at the end of the chunk is completed by the
+                    // next.
+                    None => {
+                        self.partial_char = bytes[err.valid_up_to()..].to_vec();
+                        (valid, Ok(len))
+                    }
+                    // The input up to the bad bytes is still parsed.
+                    Some(_) => (valid, Err(invalid_utf8())),
+                }
+            }
+        };
+        self.feed(valid);
+        res
+    }
+
+    /// Runs the parser over the input buffered so far, consuming what it
+    /// read if it succeeds.
+    ///
+    /// Nothing is consumed on failure, [`StreamParser::discard`] skips the
+    /// bad input.
+    pub fn parse_next(&mut self) -> Status<P::Output> {
+        if self.waiting {
+            return Status::Incomplete;
+        }
+        let stream = PartialStream::new(self.buffer.clone(), self.offset, self.pos, self.finished);
+        let mut context = Context::new(stream);
+        let res = self.parser.parse(&mut context);
+        if context.stream().reached_end() {
+            self.waiting = true;
+            return Status::Incomplete;
+        }
+        match res {
+            Ok(output) => {
+                let (offset, pos, _) = context.checkpoint();
+                self.offset = offset;
+                self.pos = pos;
+                self.errors.extend(context.take_errors());
+                Status::Parsed(output)
+            }
+            Err(err) => Status::Failed(err),
+        }
+    }
+
+    /// Like [`StreamParser::parse_next`], reading from `reader` for as long
+    /// as the input is incomplete.
+    pub fn parse_from(&mut self, reader: &mut impl Read) -> io::Result<Result<P::Output>> {
+        loop {
+            match self.parse_next() {
+                Status::Parsed(output) => return Ok(Ok(output)),
+                Status::Failed(err) => return Ok(Err(err)),
+                Status::Incomplete => self.read_from(reader)?,
+            };
+        }
+    }
+
+    /// The input buffered but not consumed yet.
+    pub fn pending(&self) -> &str {
+        &self.buffer[self.offset..]
+    }
+
+    /// Drops the pending input, e.g. after the parser failed on it.
+    pub fn discard(&mut self) {
+        self.pos += self.pending().chars().count();
+        self.offset = self.buffer.len();
+        self.waiting = false;
+    }
+
+    /// Char position of the pending input.
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Errors the parser recovered from in the runs that succeeded.
+    pub fn take_errors(&mut self) -> Vec<Error> {
+        std::mem::take(&mut self.errors)
+    }
+}
+
+fn invalid_utf8() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "stream did not contain valid UTF-8",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Status, StreamParser};
+    use crate::{consumers::*, Parser};
+
+    // The end seen by a lookahead does not count once the path fails before it.
+    #[test]
+    fn ends_backtracked_from() {
+        let mut parser = StreamParser::new(letter().many().look_ahead().pair(char('!')));
+        parser.feed("ab");
+        assert!(matches!(parser.parse_next(), Status::Failed(err) if err.pos == 0));
+
+        // A prefix failing at the end might match with more input.
+        let mut parser = StreamParser::new(string("<=").or(string("<")));
+        parser.feed("<");
+        assert!(matches!(parser.parse_next(), Status::Incomplete));
+        parser.feed("=");
+        assert!(matches!(parser.parse_next(), Status::Parsed(op) if &*op == "<="));
+
+        let mut parser = StreamParser::new(letter().many().pair(char('!')));
+        parser.feed("ab");
+        assert!(matches!(parser.parse_next(), Status::Incomplete));
+        parser.feed("!?");
+        assert!(
+            matches!(parser.parse_next(), Status::Parsed((letters, _)) if letters == ['a', 'b'])
+        );
+        assert_eq!(parser.pending(), "?");
+    }
+
+    #[test]
+    fn resumes_after_last_unit() {
+        let mut words = StreamParser::new(whitespaces().use_right(identifier()));
+        let input = "alpha beta gamma ".repeat(100);
+        words.feed(&input);
+        for i in 0..300 {
+            let Status::Parsed(word) = words.parse_next() else {
+                panic!("word {} not parsed", i);
+            };
+            assert_eq!(&*word, ["alpha", "beta", "gamma"][i % 3]);
+        }
+        assert_eq!(words.pending(), " ");
+        assert_eq!(words.pos(), input.len() - 1);
+        words.feed("del");
+        assert!(matches!(words.parse_next(), Status::Incomplete));
+        assert_eq!(words.pending(), " del");
+        words.finish();
+        assert!(matches!(words.parse_next(), Status::Parsed(word) if &*word == "del"));
+        assert_eq!(words.pos(), input.len() + 3);
+        words.discard();
+        assert_eq!(words.pending(), "");
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    cell::OnceCell,
+    rc::{Rc, Weak},
+    sync::{self, Arc, OnceLock},
+};
+
+use super::{Any, Context, Parser, Result, Stream, SyncAny};
+
+/// Builds a parser that refers to itself.
+///
+/// `f` gets a handle to the parser being built and is called once, so the
+/// grammar is built once however deep the input nests.
+pub fn recursive<'a, S, O, F, P>(f: F) -> Recursive<'a, S, O>
+where
+    S: Stream,
+    F: FnOnce(Recursive<'a, S, O>) -> P,
+    P: 'a + Parser<Input = S, Output = O>,
+{
+    let node = Rc::new(OnceCell::new());
+    let this = Recursive {
+        node: Link::Weak(Rc::downgrade(&node)),
+    };
+    let _ = node.set(f(this).to_any());
+    Recursive {
+        node: Link::Strong(node),
+    }
+}
+
+type Node<'a, S, O> = OnceCell<Any<'a, S, O>>;
+
+// The handle given to `f` is weak, so the parser does not own itself.
+enum Link<'a, S, O> {
+    Strong(Rc<Node<'a, S, O>>),
+    Weak(Weak<Node<'a, S, O>>),
+}
+
+/// Recursive
+pub struct Recursive<'a, S, O> {
+    node: Link<'a, S, O>,
+}
+
+impl<'a, S, O> Clone for Recursive<'a, S, O> {
+    fn clone(&self) -> Self {
+        let node = match &self.node {
+            Link::Strong(node) => Link::Strong(node.clone()),
+            Link::Weak(node) => Link::Weak(node.clone()),
+        };
+        Self { node }
+    }
+}
+
+impl<'a, S, O> Parser for Recursive<'a, S, O>
+where
+    S: Stream,
+{
+    type Input = S;
+    type Output = O;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        let node = match &self.node {
+            Link::Strong(node) => node.clone(),
+            Link::Weak(node) => node.upgrade().expect("recursive parser dropped"),
+        };
+        let parser = node.get().expect("recursive parser used while built");
+        parser.parse(context)
+    }
+}
+
+/// Like `recursive`, but `Send` and `Sync`, so a recursive grammar can be
+/// built once and shared between threads.
+pub fn sync_recursive<'a, S, O, F, P>(f: F) -> SyncRecursive<'a, S, O>
+where
+    S: Stream,
+    F: FnOnce(SyncRecursive<'a, S, O>) -> P,
+    P: 'a + Send + Sync + Parser<Input = S, Output = O>,
+{
+    let node = Arc::new(OnceLock::new());
+    let this = SyncRecursive {
+        node: SyncLink::Weak(Arc::downgrade(&node)),
+    };
+    let _ = node.set(f(this).to_sync_any());
+    SyncRecursive {
+        node: SyncLink::Strong(node),
+    }
+}
+
+type SyncNode<'a, S, O> = OnceLock<SyncAny<'a, S, O>>;
+
+enum SyncLink<'a, S, O> {
+    Strong(Arc<SyncNode<'a, S, O>>),
+    Weak(sync::Weak<SyncNode<'a, S, O>>),
+}
+
+/// SyncRecursive
+pub struct SyncRecursive<'a, S, O> {
+    node: SyncLink<'a, S, O>,
+}
+
+impl<'a, S, O> Clone for SyncRecursive<'a, S, O> {
+    fn clone(&self) -> Self {
+        let node = match &self.node {
+            SyncLink::Strong(node) => SyncLink::Strong(node.clone()),
+            SyncLink::Weak(node) => SyncLink::Weak(node.clone()),
+        };
+        Self { node }
+    }
+}
+
+impl<'a, S, O> Parser for SyncRecursive<'a, S, O>
+where
+    S: Stream,
+{
+    type Input = S;
+    type Output = O;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        let node = match &self.node {
+            SyncLink::Strong(node) => node.clone(),
+            SyncLink::Weak(node) => node.upgrade().expect("recursive parser dropped"),
+        };
+        let parser = node.get().expect("recursive parser used while built");
+        parser.parse(context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::{consumers::*, SharedStrStream};
+
+    // Depth of nested parentheses, built once for every thread.
+    fn depth() -> &'static SyncRecursive<'static, SharedStrStream, usize> {
+        static PARSER: OnceLock<SyncRecursive<'static, SharedStrStream, usize>> = OnceLock::new();
+        PARSER.get_or_init(|| {
+            sync_recursive(|depth| {
+                depth
+                    .many()
+                    .between(char('('), char(')'))
+                    .map(|depths| 1 + depths.into_iter().max().unwrap_or(0))
+            })
+        })
+    }
+
+    #[test]
+    fn shared_between_threads() {
+        let depths: Vec<_> = thread::scope(|scope| {
+            let handles: Vec<_> = (1..=8)
+                .map(|n| {
+                    scope.spawn(move || {
+                        let input = "()(".repeat(n) + &")".repeat(n);
+                        let input = SharedStrStream::new(format!("({})", input));
+                        Context::new(input).parse_by(depth().clone()).unwrap()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(depths, [2, 3, 4, 5, 6, 7, 8, 9]);
+    }
+}
@@ -2,7 +2,7 @@ use std::{borrow::Cow, fmt::Display};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Error {
     pub pos: usize,
     pub msg: Cow<'static, str>,
@@ -0,0 +1,269 @@
+//! User state.
+//!
+//! [`Stateful`] wraps a stream with a state of the user, such as a symbol
+//! table or a nesting depth, which parsers read and update with
+//! [`get_state`], [`put_state`] and [`modify_state`]. The state is part of
+//! every checkpoint, so it is rolled back with the input whenever a parser
+//! backtracks.
+//!
+//! ```
+//! use parsec::{consumers::*, get_state, modify_state, Context, Parser, Stateful, StrStream};
+//!
+//! type Input<'a, U> = Stateful<StrStream<'a>, U>;
+//!
+//! // The depth of the deepest parentheses.
+//! fn depth<'a>() -> impl Parser<Input = Input<'a, (u32, u32)>, Output = u32> {
+//!     let open = char('(').use_left(modify_state(|(depth, max): &mut (u32, u32)| {
+//!         *depth += 1;
+//!         *max = (*max).max(*depth);
+//!     }));
+//!     let close = char(')').use_left(modify_state(|(depth, _): &mut (u32, u32)| *depth -= 1));
+//!     open.or(close).many().use_right(get_state()).map(|(_, max)| max)
+//! }
+//!
+//! assert_eq!(Context::with_state("(()(()))", (0, 0)).parse_by(depth()).unwrap(), 3);
+//!
+//! // The state is rolled back with the `(` when `!` is missing.
+//! fn count<'a>() -> impl Parser<Input = Input<'a, u32>, Output = u32> {
+//!     let open = char('(').use_left(modify_state(|count: &mut u32| *count += 1));
+//!     open.use_left(char('!')).or(char('(')).use_right(get_state())
+//! }
+//!
+//! assert_eq!(Context::with_state("(!", 0).parse_by(count()).unwrap(), 1);
+//! assert_eq!(Context::with_state("(", 0).parse_by(count()).unwrap(), 0);
+//! ```
+//!
+//! Checkpoints share the state rather than copy it, so reading it costs
+//! nothing. An update copies it if a checkpoint still shares it, which is
+//! nearly always the case as every parser takes one: a state updated often is
+//! best kept small, or in persistent structures. A `memo()` or `left_rec`
+//! result is reused only if the state is the same as when it was parsed.
+
+use std::{marker::PhantomData, rc::Rc};
+
+use super::{Context, Parser, Result, Sliceable, Stream, TextStream};
+
+pub fn update_state<S, U, F, T>(f: F) -> UpdateState<S, U, F>
+where
+    F: Fn(&mut U) -> T,
+{
+    UpdateState::new(f)
+}
+
+pub fn get_state<S, U>() -> impl Parser<Input = Stateful<S, U>, Output = U>
+where
+    S: Stream,
+    U: Clone,
+{
+    GetState {
+        marker: PhantomData,
+    }
+}
+
+pub fn put_state<S, U>(state: U) -> impl Parser<Input = Stateful<S, U>, Output = ()>
+where
+    S: Stream,
+    U: Clone,
+{
+    update_state(move |s: &mut U| *s = state.clone())
+}
+
+pub fn modify_state<S, U, F>(f: F) -> impl Parser<Input = Stateful<S, U>, Output = ()>
+where
+    S: Stream,
+    U: Clone,
+    F: Clone + Fn(&mut U),
+{
+    update_state(f)
+}
+
+/// Stateful
+///
+/// A stream carrying a user state along.
+#[derive(Debug, Clone)]
+pub struct Stateful<S, U> {
+    stream: S,
+    // Shared with checkpoints, copied on write
+    state: Rc<U>,
+}
+
+impl<S, U> Stateful<S, U> {
+    pub fn new(stream: S, state: U) -> Self {
+        Self {
+            stream,
+            state: Rc::new(state),
+        }
+    }
+
+    pub fn state(&self) -> &U {
+        &self.state
+    }
+}
+
+impl<S, U> Stateful<S, U>
+where
+    U: Clone,
+{
+    pub fn state_mut(&mut self) -> &mut U {
+        Rc::make_mut(&mut self.state)
+    }
+
+    pub fn into_state(self) -> U {
+        Rc::try_unwrap(self.state).unwrap_or_else(|state| (*state).clone())
+    }
+}
+
+impl<S, U> Stream for Stateful<S, U>
+where
+    S: Stream,
+    U: Clone,
+{
+    type Item = S::Item;
+    type Checkpoint = (S::Checkpoint, Rc<U>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.stream.next()
+    }
+
+    fn pos(&self) -> usize {
+        self.stream.pos()
+    }
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        (self.stream.checkpoint(), self.state.clone())
+    }
+
+    fn reset(&mut self, (checkpoint, state): Self::Checkpoint) {
+        self.stream.reset(checkpoint);
+        self.state = state;
+    }
+
+    fn backtrack(&mut self, (checkpoint, state): Self::Checkpoint) {
+        self.stream.backtrack(checkpoint);
+        self.state = state;
+    }
+
+    // Updates copy the state, so an unchanged one is still shared.
+    fn same_state(&self, (_, state): &Self::Checkpoint) -> bool {
+        Rc::ptr_eq(&self.state, state)
+    }
+}
+
+impl<S, U> Sliceable for Stateful<S, U>
+where
+    S: Sliceable,
+    U: Clone,
+{
+    type Slice = S::Slice;
+
+    fn slice_since(&self, (checkpoint, _): &Self::Checkpoint) -> Self::Slice {
+        self.stream.slice_since(checkpoint)
+    }
+}
+
+impl<S, U> TextStream for Stateful<S, U>
+where
+    S: TextStream,
+    U: Clone,
+{
+    fn as_str(&self) -> &str {
+        self.stream.as_str()
+    }
+
+    fn advance(&mut self, len: usize) {
+        self.stream.advance(len)
+    }
+}
+
+// Reads the state without copying it.
+#[derive(Debug, Clone)]
+struct GetState<S, U> {
+    marker: PhantomData<fn() -> (S, U)>,
+}
+
+impl<S, U> Parser for GetState<S, U>
+where
+    S: Stream,
+    U: Clone,
+{
+    type Input = Stateful<S, U>;
+    type Output = U;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        Ok(context.state().clone())
+    }
+}
+
+/// UpdateState
+#[derive(Debug, Clone)]
+pub struct UpdateState<S, U, F> {
+    f: F,
+    marker: PhantomData<fn() -> (S, U)>,
+}
+
+impl<S, U, F> UpdateState<S, U, F> {
+    fn new(f: F) -> Self {
+        Self {
+            f,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<S, U, F, T> Parser for UpdateState<S, U, F>
+where
+    S: Stream,
+    U: Clone,
+    F: Clone + Fn(&mut U) -> T,
+{
+    type Input = Stateful<S, U>;
+    type Output = T;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        Ok((self.f)(context.state_mut()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::{consumers::*, StrStream};
+
+    // Counts its clones.
+    #[derive(Debug, Default)]
+    struct Counted(Rc<Cell<usize>>);
+
+    impl Clone for Counted {
+        fn clone(&self) -> Self {
+            self.0.set(self.0.get() + 1);
+            Self(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn checkpoints_share_the_state() {
+        let counted = Counted::default();
+        let clones = counted.0.clone();
+        let mut context: Context<Stateful<StrStream, _>> = Context::with_state("abc", counted);
+        // Backtracks at the end.
+        let _ = context.parse_by(letter().many());
+        assert_eq!(clones.get(), 0);
+        // Copied on the first update only while a checkpoint shares it.
+        let _ = context.parse_by(modify_state(|_: &mut Counted| ()).many());
+        assert_eq!(clones.get(), 1);
+    }
+
+    #[test]
+    fn memo_depends_on_the_state() {
+        type Input<'a> = Stateful<StrStream<'a>, u32>;
+        let read = get_state::<StrStream, u32>().use_left(char('a')).memo();
+        let parser = modify_state(|n: &mut u32| *n += 1)
+            .use_right(read.clone())
+            .use_left(char('!'))
+            .or(read);
+        let mut context: Context<Input> = Context::with_state("a", 0);
+        assert_eq!(context.parse_by(parser).unwrap(), 0);
+    }
+}
@@ -0,0 +1,530 @@
+use std::{fmt, ops::Deref, ops::Range, str::Chars, sync::Arc};
+
+/// Input of a parser.
+///
+/// Positions count the items read so far, e.g. chars of a `&str` or bytes of
+/// a `&[u8]`. A checkpoint is cheap to take and restores the stream when a
+/// parser fails.
+pub trait Stream: Clone {
+    type Item;
+    type Checkpoint: Clone;
+
+    fn next(&mut self) -> Option<Self::Item>;
+
+    fn pos(&self) -> usize;
+
+    fn checkpoint(&self) -> Self::Checkpoint;
+
+    fn reset(&mut self, checkpoint: Self::Checkpoint);
+
+    /// Restores `checkpoint` after the parse from it failed, as opposed to
+    /// `reset`, which a parser calls on its own path.
+    fn backtrack(&mut self, checkpoint: Self::Checkpoint) {
+        self.reset(checkpoint)
+    }
+
+    /// Whether anything the stream carries besides its input, such as a
+    /// user state, is as it was at `checkpoint`. A memoized result is reused
+    /// only when it is.
+    fn same_state(&self, _checkpoint: &Self::Checkpoint) -> bool {
+        true
+    }
+}
+
+/// Stream over contiguous input, so the items read since a checkpoint can be
+/// borrowed instead of collected.
+pub trait Sliceable: Stream {
+    type Slice;
+
+    fn slice_since(&self, checkpoint: &Self::Checkpoint) -> Self::Slice;
+}
+
+/// Stream over a string, whose remaining input can be matched as a `&str`,
+/// e.g. by a regex, and skipped by byte length.
+pub trait TextStream: Sliceable<Item = char> {
+    fn as_str(&self) -> &str;
+
+    /// Skips `len` bytes, which must end at a char boundary.
+    fn advance(&mut self, len: usize);
+}
+
+/// StrStream
+///
+/// Chars of a string slice.
+#[derive(Debug, Clone)]
+pub struct StrStream<'a> {
+    str: &'a str,
+    // Byte offset of the next char
+    offset: usize,
+    pos: usize,
+}
+
+impl<'a> StrStream<'a> {
+    pub fn new(str: &'a str) -> Self {
+        Self {
+            str,
+            offset: 0,
+            pos: 0,
+        }
+    }
+
+    /// The remaining input.
+    pub fn as_str(&self) -> &'a str {
+        &self.str[self.offset..]
+    }
+
+    fn chars(&self) -> Chars<'a> {
+        self.as_str().chars()
+    }
+}
+
+impl<'a> From<&'a str> for StrStream<'a> {
+    fn from(str: &'a str) -> Self {
+        Self::new(str)
+    }
+}
+
+impl<'a> Stream for StrStream<'a> {
+    type Item = char;
+    type Checkpoint = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let char = self.chars().next()?;
+        self.offset += char.len_utf8();
+        self.pos += 1;
+        Some(char)
+    }
+
+    fn pos(&self) -> usize {
+        self.pos
+    }
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        (self.offset, self.pos)
+    }
+
+    fn reset(&mut self, (offset, pos): Self::Checkpoint) {
+        self.offset = offset;
+        self.pos = pos;
+    }
+}
+
+impl<'a> Sliceable for StrStream<'a> {
+    type Slice = &'a str;
+
+    fn slice_since(&self, (offset, _): &Self::Checkpoint) -> Self::Slice {
+        &self.str[*offset..self.offset]
+    }
+}
+
+impl<'a> TextStream for StrStream<'a> {
+    fn as_str(&self) -> &str {
+        StrStream::as_str(self)
+    }
+
+    fn advance(&mut self, len: usize) {
+        let skipped = &self.as_str()[..len];
+        self.pos += skipped.chars().count();
+        self.offset += len;
+    }
+}
+
+/// SharedStrStream
+///
+/// Chars of a reference-counted string. Unlike `StrStream` it borrows
+/// nothing, so parsers over it can be built once, kept in a `static` and
+/// used for any input.
+#[derive(Debug, Clone)]
+pub struct SharedStrStream {
+    str: Arc<str>,
+    // Byte offset of the next char
+    offset: usize,
+    pos: usize,
+}
+
+impl SharedStrStream {
+    pub fn new(str: impl Into<Arc<str>>) -> Self {
+        Self {
+            str: str.into(),
+            offset: 0,
+            pos: 0,
+        }
+    }
+
+    /// The remaining input.
+    pub fn as_str(&self) -> &str {
+        &self.str[self.offset..]
+    }
+}
+
+impl Stream for SharedStrStream {
+    type Item = char;
+    type Checkpoint = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let char = self.as_str().chars().next()?;
+        self.offset += char.len_utf8();
+        self.pos += 1;
+        Some(char)
+    }
+
+    fn pos(&self) -> usize {
+        self.pos
+    }
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        (self.offset, self.pos)
+    }
+
+    fn reset(&mut self, (offset, pos): Self::Checkpoint) {
+        self.offset = offset;
+        self.pos = pos;
+    }
+}
+
+impl Sliceable for SharedStrStream {
+    type Slice = SharedStr;
+
+    fn slice_since(&self, (offset, _): &Self::Checkpoint) -> Self::Slice {
+        SharedStr {
+            str: self.str.clone(),
+            range: *offset..self.offset,
+        }
+    }
+}
+
+impl TextStream for SharedStrStream {
+    fn as_str(&self) -> &str {
+        SharedStrStream::as_str(self)
+    }
+
+    fn advance(&mut self, len: usize) {
+        let skipped = &self.as_str()[..len];
+        self.pos += skipped.chars().count();
+        self.offset += len;
+    }
+}
+
+/// PartialStream
+///
+/// Chars of the input received so far by a `StreamParser`, which may go on.
+/// Reaching the end of it is noted, since a parser doing so might have read
+/// more, or failed otherwise, had more input been there. A failed attempt
+/// backtracked from keeps the note only if it failed at the end, so an end
+/// seen by e.g. a lookahead on a path that failed earlier is forgotten.
+#[derive(Debug, Clone)]
+pub struct PartialStream {
+    str: Arc<str>,
+    // Byte offset of the next char
+    offset: usize,
+    pos: usize,
+    // Whether the input ends here
+    complete: bool,
+    reached_end: bool,
+    // Whether the last read ran into the end
+    end_read: bool,
+}
+
+impl PartialStream {
+    pub(super) fn new(str: Arc<str>, offset: usize, pos: usize, complete: bool) -> Self {
+        Self {
+            str,
+            offset,
+            pos,
+            complete,
+            reached_end: false,
+            end_read: false,
+        }
+    }
+
+    /// The remaining input.
+    pub fn as_str(&self) -> &str {
+        &self.str[self.offset..]
+    }
+
+    /// Whether a parser ran into the end of the input received so far.
+    pub fn reached_end(&self) -> bool {
+        self.reached_end
+    }
+}
+
+impl Stream for PartialStream {
+    type Item = char;
+    type Checkpoint = (usize, usize, bool);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Some(char) = self.as_str().chars().next() else {
+            self.reached_end = !self.complete;
+            self.end_read = true;
+            return None;
+        };
+        self.end_read = false;
+        self.offset += char.len_utf8();
+        self.pos += 1;
+        Some(char)
+    }
+
+    fn pos(&self) -> usize {
+        self.pos
+    }
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        (self.offset, self.pos, self.reached_end)
+    }
+
+    // The end reached by the path up to `checkpoint` is kept, as for a
+    // memoized result.
+    fn reset(&mut self, (offset, pos, reached_end): Self::Checkpoint) {
+        self.offset = offset;
+        self.pos = pos;
+        self.reached_end |= reached_end;
+    }
+
+    // A failure at the end might be a success with more input, one before it
+    // would not.
+    fn backtrack(&mut self, (offset, pos, reached_end): Self::Checkpoint) {
+        if !self.end_read {
+            self.reached_end = reached_end;
+        }
+        self.offset = offset;
+        self.pos = pos;
+    }
+}
+
+impl Sliceable for PartialStream {
+    type Slice = SharedStr;
+
+    fn slice_since(&self, (offset, ..): &Self::Checkpoint) -> Self::Slice {
+        SharedStr {
+            str: self.str.clone(),
+            range: *offset..self.offset,
+        }
+    }
+}
+
+/// Part of the string of a `SharedStrStream`, sharing it without copying.
+#[derive(Debug, Clone)]
+pub struct SharedStr {
+    str: Arc<str>,
+    range: Range<usize>,
+}
+
+impl Deref for SharedStr {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.str[self.range.clone()]
+    }
+}
+
+impl AsRef<str> for SharedStr {
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl fmt::Display for SharedStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self)
+    }
+}
+
+/// SliceStream
+///
+/// Items of a slice, such as the bytes of a binary format or the tokens of a
+/// lexer.
+#[derive(Debug, Clone)]
+pub struct SliceStream<'a, T> {
+    slice: &'a [T],
+    pos: usize,
+}
+
+impl<'a, T> SliceStream<'a, T> {
+    pub fn new(slice: &'a [T]) -> Self {
+        Self { slice, pos: 0 }
+    }
+
+    /// The remaining input.
+    pub fn as_slice(&self) -> &'a [T] {
+        &self.slice[self.pos..]
+    }
+}
+
+impl<'a, T> Stream for SliceStream<'a, T>
+where
+    T: Clone,
+{
+    type Item = T;
+    type Checkpoint = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.slice.get(self.pos)?.clone();
+        self.pos += 1;
+        Some(item)
+    }
+
+    fn pos(&self) -> usize {
+        self.pos
+    }
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        self.pos
+    }
+
+    fn reset(&mut self, pos: Self::Checkpoint) {
+        self.pos = pos;
+    }
+}
+
+impl<'a, T> Sliceable for SliceStream<'a, T>
+where
+    T: Clone,
+{
+    type Slice = &'a [T];
+
+    fn slice_since(&self, pos: &Self::Checkpoint) -> Self::Slice {
+        &self.slice[*pos..self.pos]
+    }
+}
+
+/// IterStream
+///
+/// Items of an iterator, which is cloned for every checkpoint.
+#[derive(Debug, Clone)]
+pub struct IterStream<I> {
+    iter: I,
+    pos: usize,
+}
+
+impl<I> IterStream<I> {
+    pub fn new(iter: I) -> Self {
+        Self { iter, pos: 0 }
+    }
+}
+
+impl<I> Stream for IterStream<I>
+where
+    I: Iterator + Clone,
+{
+    type Item = I::Item;
+    type Checkpoint = Self;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        self.pos += 1;
+        Some(item)
+    }
+
+    fn pos(&self) -> usize {
+        self.pos
+    }
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        self.clone()
+    }
+
+    fn reset(&mut self, checkpoint: Self::Checkpoint) {
+        *self = checkpoint;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{consumers::*, Context, Parser};
+
+    // Keywords sharing their first char, so every alternative but the first
+    // resets the stream to the checkpoint before it.
+    fn keyword<S>() -> impl Parser<Input = S, Output = u8>
+    where
+        S: Stream<Item: Into<char>>,
+    {
+        string("if")
+            .map(|_| 1)
+            .or(string("in").map(|_| 2))
+            .or(char('i').use_left(char('s')).map(|_| 3))
+    }
+
+    fn keywords<S>(stream: S) -> (Vec<u8>, usize)
+    where
+        S: Stream<Item: Into<char>>,
+    {
+        let mut context = Context::new(stream);
+        let keywords = context
+            .parse_by(keyword().use_left(char(' ').optional()).many())
+            .unwrap();
+        (keywords, context.pos())
+    }
+
+    #[test]
+    fn chars_of_any_stream() {
+        let input = "in is if ix";
+        let chars: Vec<char> = input.chars().collect();
+        let expected = (vec![2, 3, 1], 9);
+        assert_eq!(keywords(StrStream::new(input)), expected);
+        assert_eq!(keywords(SliceStream::new(input.as_bytes())), expected);
+        assert_eq!(keywords(SliceStream::new(&chars)), expected);
+        assert_eq!(keywords(IterStream::new(input.chars())), expected);
+        assert_eq!(keywords(IterStream::new(input.bytes())), expected);
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Token {
+        Num(i64),
+        Plus,
+        Minus,
+    }
+
+    // Tokens of `n (+|- n)*`, combined from left to right.
+    fn sum<S>() -> impl Parser<Input = S, Output = i64>
+    where
+        S: Stream<Item = Token>,
+    {
+        let num = any().and_then(|token| match token {
+            Token::Num(n) => Ok(n),
+            _ => Err("expect number"),
+        });
+        let op = item(Token::Plus)
+            .map(|_| 1)
+            .or(item(Token::Minus).map(|_| -1))
+            .map(|sign| move |lhs: i64, rhs: i64| lhs + sign * rhs);
+        num.chainl1(op)
+    }
+
+    #[test]
+    fn tokens_of_any_stream() {
+        use Token::*;
+        let tokens = [Num(10), Minus, Num(4), Plus, Num(1), Minus];
+        let mut context = Context::from_slice(&tokens);
+        assert_eq!(context.parse_by(sum()).unwrap(), 7);
+        // The trailing `-` is read back.
+        assert_eq!(context.pos(), 5);
+        assert_eq!(context.stream().as_slice(), [Minus]);
+
+        let mut context = Context::new(IterStream::new(tokens.iter().cloned()));
+        assert_eq!(context.parse_by(sum()).unwrap(), 7);
+        assert_eq!(context.pos(), 5);
+        assert_eq!(context.next().unwrap(), Minus);
+
+        let err = Context::from_slice(&[Plus]).parse_by(sum()).unwrap_err();
+        assert_eq!((err.pos, &*err.msg), (1, "expect number"));
+    }
+
+    #[test]
+    fn checkpoint_and_reset() {
+        let mut stream = SliceStream::new(b"abc");
+        let checkpoint = stream.checkpoint();
+        assert_eq!((stream.next(), stream.next()), (Some(b'a'), Some(b'b')));
+        assert_eq!(stream.slice_since(&checkpoint), b"ab");
+        stream.reset(checkpoint);
+        assert_eq!((stream.pos(), stream.as_slice()), (0, &b"abc"[..]));
+
+        let mut stream = IterStream::new(1..4);
+        let checkpoint = stream.checkpoint();
+        let items: Vec<_> = std::iter::from_fn(|| stream.next()).collect();
+        assert_eq!(items, [1, 2, 3]);
+        assert_eq!((stream.pos(), stream.next()), (3, None));
+        stream.reset(checkpoint);
+        assert_eq!((stream.pos(), stream.next()), (0, Some(1)));
+    }
+}
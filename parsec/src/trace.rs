@@ -0,0 +1,243 @@
+//! Tracing of parser execution.
+//!
+//! Once [`Context::enable_tracing`] is called, every parser wrapped by
+//! `named` records where it started and ended and whether it failed, nested
+//! under the named parser that ran it. Failed branches are kept, which shows
+//! why an alternative was picked.
+//!
+//! ```
+//! use parsec::{consumers::*, Context, Parser};
+//!
+//! let bool = string("true").or(string("false")).named("bool");
+//! let value = number().map(|_| ()).named("number").or(bool.map(|_| ())).named("value");
+//! let mut context = Context::from_str("false");
+//! context.enable_tracing();
+//! context.parse_by(value).unwrap();
+//!
+//! let trace = context.take_trace().unwrap();
+//! assert_eq!(
+//!     trace.to_text(),
+//!     "value 0..5\n  number 0..0 failed at 0: expect number\n  bool 0..5\n"
+//! );
+//! ```
+//!
+//! [`Context::enable_tracing`]: crate::Context::enable_tracing
+
+use std::{borrow::Cow, fmt::Write};
+
+use super::Error;
+
+/// Trace
+///
+/// The tree of named parsers run so far.
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+    roots: Vec<TraceNode>,
+    // Parsers being run, outermost first
+    stack: Vec<TraceNode>,
+}
+
+/// A run of a named parser.
+#[derive(Debug, Clone)]
+pub struct TraceNode {
+    pub name: Cow<'static, str>,
+    pub start: usize,
+    pub end: usize,
+    /// Why it failed, if it did.
+    pub error: Option<Error>,
+    pub children: Vec<TraceNode>,
+}
+
+// Visits of a depth-first walk.
+enum Visit<'a> {
+    Enter(&'a TraceNode, usize),
+    Exit,
+}
+
+impl Trace {
+    /// Runs of named parsers not run by another named parser.
+    pub fn roots(&self) -> &[TraceNode] {
+        &self.roots
+    }
+
+    /// Starts a run of `name` at `start`, nested under the run not exited
+    /// yet. `named` calls it; a hand-written parser that runs its parts
+    /// without `named` may too, positions being its own to choose.
+    pub fn enter(&mut self, name: impl Into<Cow<'static, str>>, start: usize) {
+        self.stack.push(TraceNode {
+            name: name.into(),
+            start,
+            end: start,
+            error: None,
+            children: Vec::new(),
+        });
+    }
+
+    /// Ends the last run entered.
+    pub fn exit(&mut self, end: usize, error: Option<Error>) {
+        let Some(mut node) = self.stack.pop() else {
+            return;
+        };
+        node.end = end;
+        node.error = error;
+        match self.stack.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None => self.roots.push(node),
+        }
+    }
+
+    /// Adds the runs of `other` as if run now, e.g. those of a later pass
+    /// over the same input.
+    pub fn append(&mut self, other: Trace) {
+        match self.stack.last_mut() {
+            Some(parent) => parent.children.extend(other.roots),
+            None => self.roots.extend(other.roots),
+        }
+    }
+
+    /// One line per run, indented by nesting.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        self.walk(|visit| {
+            if let Visit::Enter(node, depth) = visit {
+                let indent = "  ".repeat(depth);
+                let _ = writeln!(out, "{}{}", indent, node.label());
+            }
+        });
+        out
+    }
+
+    /// Nested lists, failed runs in red and collapsed.
+    pub fn to_html(&self) -> String {
+        let mut out = String::from("<ul class=\"trace\">\n");
+        self.walk(|visit| match visit {
+            Visit::Enter(node, _) => {
+                let (color, open) = match node.error {
+                    Some(_) => ("#c00", ""),
+                    None => ("#080", " open"),
+                };
+                let _ = writeln!(
+                    out,
+                    "<li><details{}><summary style=\"color: {}\">{}</summary><ul>",
+                    open,
+                    color,
+                    escape_html(&node.label()),
+                );
+            }
+            Visit::Exit => out.push_str("</ul></details></li>\n"),
+        });
+        out.push_str("</ul>\n");
+        out
+    }
+
+    /// A Graphviz digraph, each run pointing to the runs it made.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph trace {\n  node [shape=box];\n");
+        // Ids of the runs entered but not exited
+        let mut parents: Vec<usize> = Vec::new();
+        let mut next_id = 0;
+        self.walk(|visit| match visit {
+            Visit::Enter(node, _) => {
+                let color = if node.error.is_some() { "red" } else { "black" };
+                let _ = writeln!(
+                    out,
+                    "  n{} [label=\"{}\", color={}];",
+                    next_id,
+                    escape_dot(&node.label()),
+                    color
+                );
+                if let Some(parent) = parents.last() {
+                    let _ = writeln!(out, "  n{} -> n{};", parent, next_id);
+                }
+                parents.push(next_id);
+                next_id += 1;
+            }
+            Visit::Exit => {
+                parents.pop();
+            }
+        });
+        out.push_str("}\n");
+        out
+    }
+
+    // Walks the runs in order on an explicit stack, traces of deeply
+    // recursive grammars are deep too.
+    fn walk<'a>(&'a self, mut f: impl FnMut(Visit<'a>)) {
+        let mut stack: Vec<Visit<'a>> = self
+            .roots
+            .iter()
+            .rev()
+            .map(|node| Visit::Enter(node, 0))
+            .collect();
+        while let Some(visit) = stack.pop() {
+            if let Visit::Enter(node, depth) = visit {
+                stack.push(Visit::Exit);
+                stack.extend(
+                    node.children
+                        .iter()
+                        .rev()
+                        .map(|child| Visit::Enter(child, depth + 1)),
+                );
+            }
+            f(visit);
+        }
+    }
+}
+
+impl TraceNode {
+    fn label(&self) -> String {
+        match &self.error {
+            Some(err) => format!(
+                "{} {}..{} failed at {}: {}",
+                self.name, self.start, self.end, err.pos, err.msg
+            ),
+            None => format!("{} {}..{}", self.name, self.start, self.end),
+        }
+    }
+}
+
+fn escape_html(str: &str) -> String {
+    str.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// A quoted Graphviz string only escapes `"`, and `\` starts its own escapes.
+fn escape_dot(str: &str) -> String {
+    str.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{consumers::*, Context, Parser};
+
+    #[test]
+    fn dot_labels_are_escaped() {
+        let mut context = Context::from_str("x");
+        context.enable_tracing();
+        let parser = char('x').named(r#"say "x" \ é"#);
+        context.parse_by(parser).unwrap();
+        let dot = context.take_trace().unwrap().to_dot();
+        assert!(
+            dot.contains(r#"n0 [label="say \"x\" \\ é 0..1", color=black];"#),
+            "{}",
+            dot
+        );
+    }
+
+    #[test]
+    fn append_nests_under_the_current_run() {
+        let mut later = Trace::default();
+        later.enter("b", 1);
+        later.exit(2, None);
+        let mut trace = Trace::default();
+        trace.enter("a", 0);
+        trace.append(later.clone());
+        trace.exit(2, None);
+        trace.append(later);
+        assert_eq!(trace.to_text(), "a 0..2\n  b 1..2\nb 1..2\n");
+    }
+}
@@ -0,0 +1,166 @@
+//! Line-delimited JSON protocol.
+//!
+//! Requests (in `--json-requests` mode), one per line:
+//!
+//! ```json
+//! {"id": 1, "expr": "(+ 1 2)"}
+//! ```
+//!
+//! Responses, one per line. `id` echoes the request id and is `null` in
+//! `--json` mode. Spans are character offsets into `expr`, or into the whole
+//! input in `--json` mode.
+//!
+//! ```json
+//! {"id": 1, "status": "ok", "value": 3}
+//! {"id": 2, "status": "error", "error": {"kind": "DivisionByZero", "message": "Division By Zero", "span": {"start": 0, "end": 7}}}
+//! ```
+
+use s_expression::{Error, Result, SourceForm, Span, Val};
+use serde_json::{json, Value};
+
+pub fn handle_request(line: &str, eval: impl FnOnce(&str) -> Result<Val>) -> Value {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(err) => return invalid_request(Value::Null, format!("{}", err)),
+    };
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let Some(expr) = request.get("expr").and_then(Value::as_str) else {
+        return invalid_request(id, "expect string field \"expr\"".into());
+    };
+    response(id, eval(expr))
+}
+
+pub fn response(id: impl Into<Option<Value>>, res: Result<Val>) -> Value {
+    let id = id.into().unwrap_or(Value::Null);
+    match res {
+        Ok(val) => json!({ "id": id, "status": "ok", "value": value(val) }),
+        Err(err) => json!({ "id": id, "status": "error", "error": error(&err) }),
+    }
+}
+
+/// Response to a form read in `--json` mode, with its spans moved from the
+/// text of the form to the whole input.
+pub fn form_response(form: &SourceForm, res: Result<Val>) -> Value {
+    response(None, res.map_err(|err| err.offset_by(form.span.start)))
+}
+
+pub fn emit(value: &Value) {
+    println!("{}", value);
+}
+
+fn value(val: Val) -> Value {
+    match val {
+        Val::Int(val) => json!(val),
+        Val::Bool(val) => json!(val),
+    }
+}
+
+fn error(err: &Error) -> Value {
+    json!({
+        "kind": err.kind(),
+        "message": format!("{}", err),
+        "span": span(err.span()),
+    })
+}
+
+fn span(span: Span) -> Value {
+    json!({ "start": span.start, "end": span.end })
+}
+
+fn invalid_request(id: Value, message: String) -> Value {
+    json!({
+        "id": id,
+        "status": "error",
+        "error": { "kind": "InvalidRequest", "message": message, "span": null },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use s_expression::{FormReader, FormStatus};
+
+    use super::*;
+
+    // The response to `line`, as read back from the line emitted for it.
+    fn round_trip(line: &str) -> Value {
+        let response = handle_request(line, |expr| s_expression::run(expr)).to_string();
+        assert!(!response.contains('\n'));
+        serde_json::from_str(&response).unwrap()
+    }
+
+    #[test]
+    fn requests() {
+        assert_eq!(
+            round_trip(r#"{"id": 1, "expr": "(+ 1 2)"}"#),
+            json!({ "id": 1, "status": "ok", "value": 3 })
+        );
+        assert_eq!(
+            round_trip(r#"{"id": "a", "expr": "(< 1 2)"}"#),
+            json!({ "id": "a", "status": "ok", "value": true })
+        );
+        assert_eq!(
+            round_trip(r#"{"expr": "(let (x 2) (* x x))"}"#),
+            json!({ "id": null, "status": "ok", "value": 4 })
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            round_trip(r#"{"id": 2, "expr": "(/ 1 0)"}"#),
+            json!({
+                "id": 2,
+                "status": "error",
+                "error": {
+                    "kind": "DivisionByZero",
+                    "message": "Division By Zero",
+                    "span": { "start": 0, "end": 7 },
+                },
+            })
+        );
+        let response = round_trip(r#"{"id": 3, "expr": "(+ 1 x)"}"#);
+        assert_eq!(response["error"]["kind"], "UnboundIdentifier");
+        assert_eq!(response["error"]["span"], json!({ "start": 5, "end": 6 }));
+    }
+
+    #[test]
+    fn invalid_requests() {
+        let response = round_trip("(+ 1 2)");
+        assert_eq!(response["id"], Value::Null);
+        assert_eq!(response["error"]["kind"], "InvalidRequest");
+        assert_eq!(response["error"]["span"], Value::Null);
+        assert_eq!(
+            round_trip(r#"{"id": 4, "expr": 5}"#),
+            json!({
+                "id": 4,
+                "status": "error",
+                "error": {
+                    "kind": "InvalidRequest",
+                    "message": "expect string field \"expr\"",
+                    "span": null,
+                },
+            })
+        );
+    }
+
+    // Spans of forms are offsets into the whole input.
+    #[test]
+    fn form_spans() {
+        let mut forms = FormReader::new();
+        forms.feed("(+ 1 2)\n  (/ 1\n 0) (+ 1 x)");
+        forms.finish();
+        let mut spans = Vec::new();
+        while let FormStatus::Form(form) = forms.next_form() {
+            let response = form_response(&form, s_expression::run(&form.text));
+            spans.push(response["error"]["span"].clone());
+        }
+        assert_eq!(
+            spans,
+            [
+                Value::Null,
+                json!({ "start": 10, "end": 18 }),
+                json!({ "start": 24, "end": 25 }),
+            ]
+        );
+    }
+}
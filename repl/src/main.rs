@@ -1,28 +1,130 @@
 #![feature(let_else)]
 
-use s_expression;
-use std::io::{self, Write};
+mod json;
+
+use s_expression::{self, CancellationToken, FormReader, FormStatus, Limits};
+use std::{
+    io::{self, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+// Whether an evaluation is running, so Ctrl-C interrupts it instead of
+// quitting.
+static EVALUATING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Plain,
+    // One JSON object per result or error
+    Json,
+    // As `Json`, and every input line is a JSON request
+    JsonRequests,
+}
 
 fn main() {
+    let mode = match std::env::args().nth(1).as_deref() {
+        None => Mode::Plain,
+        Some("--json") => Mode::Json,
+        Some("--json-requests") => Mode::JsonRequests,
+        Some(arg) => {
+            eprintln!("unknown argument: {}", arg);
+            eprintln!("usage: repl [--json | --json-requests]");
+            std::process::exit(2);
+        }
+    };
+
+    let token = install_interrupt_handler();
+    let limits = Limits::new().cancellation(token.clone());
+    let eval = |exp: &str| {
+        token.reset();
+        EVALUATING.store(true, Ordering::SeqCst);
+        let res = s_expression::run_with_limits(exp, &limits);
+        EVALUATING.store(false, Ordering::SeqCst);
+        res
+    };
+
+    if mode == Mode::JsonRequests {
+        loop {
+            let line = read_line(None);
+            if line.is_empty() {
+                break;
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            json::emit(&json::handle_request(&line, eval));
+        }
+        return;
+    }
+
+    // Forms may span lines, each is evaluated once complete.
+    let mut forms = FormReader::new();
     loop {
-        let Some(exp) = read_exp() else { continue };
-        match s_expression::run(exp) {
-            Ok(val) => println!("{}", val),
-            Err(err) => eprintln!("{}", err),
+        match forms.next_form() {
+            FormStatus::Form(form) => match mode {
+                Mode::Plain => {
+                    match eval(&form.text) {
+                        Ok(val) => println!("{}", val),
+                        Err(err) => eprintln!("{}", err),
+                    }
+                    println!();
+                }
+                _ => json::emit(&json::form_response(&form, eval(&form.text))),
+            },
+            FormStatus::Incomplete => {
+                let prompt = match (mode, forms.is_pending()) {
+                    (Mode::Plain, false) => Some("> "),
+                    (Mode::Plain, true) => Some(".. "),
+                    _ => None,
+                };
+                let line = read_line(prompt);
+                if line.is_empty() {
+                    forms.finish();
+                } else {
+                    forms.feed(&line);
+                }
+            }
+            FormStatus::End => break,
+            // Nothing more can be read.
+            FormStatus::Failed(err) => {
+                let err = s_expression::Error::Parser(err);
+                match mode {
+                    Mode::Plain => eprintln!("{}", err),
+                    _ => json::emit(&json::response(None, Err(err))),
+                }
+                break;
+            }
         }
-        println!();
     }
 }
 
-fn read_exp() -> Option<String> {
-    print!("> ");
-    let _ = io::stdout().flush();
-    let mut exp = String::new();
-    match io::stdin().read_line(&mut exp) {
-        Ok(_) => Some(exp),
-        Err(err) => {
-            eprintln!("input error: {}", err);
-            return None;
+fn install_interrupt_handler() -> CancellationToken {
+    let token = CancellationToken::new();
+    let handler_token = token.clone();
+    let res = ctrlc::set_handler(move || {
+        if EVALUATING.load(Ordering::SeqCst) {
+            handler_token.cancel();
+        } else {
+            std::process::exit(130);
         }
+    });
+    if let Err(err) = res {
+        eprintln!("failed to install Ctrl-C handler: {}", err);
+    }
+    token
+}
+
+// Returns an empty string at end of input. Exits on an input error, which
+// reading again would most likely hit again.
+fn read_line(prompt: Option<&str>) -> String {
+    if let Some(prompt) = prompt {
+        print!("{}", prompt);
+        let _ = io::stdout().flush();
+    }
+    let mut line = String::new();
+    if let Err(err) = io::stdin().read_line(&mut line) {
+        eprintln!("input error: {}", err);
+        std::process::exit(1);
     }
+    line
 }
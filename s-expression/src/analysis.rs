@@ -0,0 +1,300 @@
+//! Source analysis for editor tooling.
+//!
+//! A source is a sequence of forms as accepted by [`Session::execute`].
+//! Offsets and spans are character offsets into the source.
+//!
+//! [`Session::execute`]: crate::Session::execute
+
+use super::{
+    evaluator,
+    expression::{Atom, Binding, Expression, ExpressionKind, Form, Globals},
+    parser, printer, Error, Limits, Span, Val,
+};
+
+// Keeps analysis of runaway sources from hanging. The evaluator needs no
+// depth limit, it keeps its continuation on the heap.
+fn limits() -> Limits {
+    Limits::new().max_steps(1_000_000)
+}
+
+/// Syntax or evaluation errors of the source.
+///
+/// All the syntax errors are reported at once; the forms are only evaluated
+/// if there are none. Forms are evaluated in order as in a [`Session`]; a
+/// form failing to evaluate does not stop the following ones from being
+/// checked. A form too costly to evaluate is not reported, it is no fault of
+/// the source.
+///
+/// [`Session`]: crate::Session
+///
+/// ```
+/// use s_expression::{analysis, Error};
+///
+/// let source = "(define x (+ 1 #))\n(if x)\n(let (y 2) (* y 3)";
+/// let errors: Vec<_> = analysis::diagnostics(source)
+///     .into_iter()
+///     .map(|err| match err {
+///         Error::Parser(err) => (err.pos, err.msg.into_owned()),
+///         err => panic!("unexpected {}", err),
+///     })
+///     .collect();
+/// assert_eq!(
+///     errors,
+///     [
+///         (15, "syntax error".to_owned()),
+///         (24, "if expression syntax error".to_owned()),
+///         (44, "expect ')'".to_owned()),
+///     ]
+/// );
+/// ```
+pub fn diagnostics(source: &str) -> Vec<Error> {
+    let (forms, mut errors) = parser::parse_forms_partial(source);
+    if !errors.is_empty() {
+        errors.sort_by_key(|err| err.pos);
+        return errors.into_iter().map(Error::Parser).collect();
+    }
+    let mut globals = Globals::new();
+    let mut errors = Vec::new();
+    for form in &forms {
+        let (name, exp) = form_exp(form);
+        match evaluator::eval_with_globals(exp, &globals, &limits()) {
+            Ok(val) => {
+                if let Some(name) = name {
+                    globals.insert(name.to_owned(), val);
+                }
+            }
+            Err(Error::StepLimitExceeded(_)) => {}
+            Err(err) => errors.push(err),
+        }
+    }
+    errors
+}
+
+/// Value of the innermost expression at `offset`, if it has no free
+/// variables and evaluates successfully. Syntax errors elsewhere in the
+/// source do not get in the way.
+pub fn hover(source: &str, offset: usize) -> Option<(Span, Val)> {
+    let (forms, _) = parser::parse_forms_partial(source);
+    let mut globals = Globals::new();
+    for form in &forms {
+        let (name, root) = form_exp(form);
+        if contains(&root.span, offset) {
+            let exp = innermost(root, offset, &mut Vec::new());
+            if !is_closed(exp, &globals) {
+                return None;
+            }
+            let val = evaluator::eval_with_globals(exp, &globals, &limits()).ok()?;
+            return Some((exp.span.clone(), val));
+        }
+        if let (Some(name), Ok(val)) = (
+            name,
+            evaluator::eval_with_globals(root, &globals, &limits()),
+        ) {
+            globals.insert(name.to_owned(), val);
+        }
+    }
+    None
+}
+
+/// Span of the binding name the variable at `offset` refers to, either a
+/// `let` binding in scope or a preceding `define`.
+pub fn definition(source: &str, offset: usize) -> Option<Span> {
+    let (forms, _) = parser::parse_forms_partial(source);
+    let idx = forms
+        .iter()
+        .position(|form| contains(&form_exp(form).1.span, offset))?;
+    let mut scope = Vec::new();
+    let exp = innermost(form_exp(&forms[idx]).1, offset, &mut scope);
+    let ExpressionKind::Atom(Atom::Var(id)) = &exp.kind else {
+        return None;
+    };
+    let defines = forms[..idx].iter().filter_map(|form| match form {
+        Form::Define(binding) => Some(binding),
+        Form::Exp(_) => None,
+    });
+    defines
+        .chain(scope)
+        .rev()
+        .find(|binding| binding.name == *id)
+        .map(|binding| binding.name_span.clone())
+}
+
+/// Pretty-printed source, at most `width` characters wide where possible.
+pub fn format(source: &str, width: usize) -> Result<String, Error> {
+    parser::parse_forms(source)
+        .map(|forms| printer::pretty_forms(&forms, width))
+        .map_err(Error::Parser)
+}
+
+/// How the source was read: token by token, including the alternatives
+/// tried and failed, e.g. to find out why a token was misread, then list by
+/// list:
+///
+/// ```
+/// let trace = s_expression::analysis::trace("(f 1x)").to_text();
+/// assert!(trace.contains("bad token 3..5"));
+/// assert!(trace.contains("list 0..6\n  atom 1..2\n  atom 3..5 failed at 3: bad token\n"));
+/// ```
+pub fn trace(source: &str) -> parsec::trace::Trace {
+    parser::trace(source)
+}
+
+fn form_exp(form: &Form) -> (Option<&str>, &Expression) {
+    match form {
+        Form::Define(binding) => (Some(&binding.name), &binding.exp),
+        Form::Exp(exp) => (None, exp),
+    }
+}
+
+// Traversal
+
+fn contains(span: &Span, offset: usize) -> bool {
+    span.start <= offset && offset < span.end
+}
+
+// Walks down to the innermost expression containing `offset`, pushing the
+// bindings in scope there. Within a `let`, a binding is evaluated where the
+// variable is used, so it sees every binding of the `let`.
+fn innermost<'a>(
+    mut exp: &'a Expression,
+    offset: usize,
+    scope: &mut Vec<&'a Binding>,
+) -> &'a Expression {
+    loop {
+        let child = match &exp.kind {
+            ExpressionKind::Atom(_) | ExpressionKind::Error => None,
+            ExpressionKind::Oper { lhs, rhs, .. } => [lhs, rhs]
+                .into_iter()
+                .find(|child| contains(&child.span, offset)),
+            ExpressionKind::If {
+                pred,
+                then,
+                or_else,
+            } => [pred, then, or_else]
+                .into_iter()
+                .find(|child| contains(&child.span, offset)),
+            ExpressionKind::Let { bindings, exp } => {
+                let idx = bindings
+                    .iter()
+                    .position(|binding| contains(&binding.exp.span, offset));
+                let child = match idx {
+                    Some(idx) => Some(&bindings[idx].exp),
+                    None if contains(&exp.span, offset) => Some(exp),
+                    None => None,
+                };
+                if child.is_some() {
+                    scope.extend(bindings);
+                }
+                child
+            }
+        };
+        match child {
+            Some(child) => exp = child,
+            None => return exp,
+        }
+    }
+}
+
+// Whether every variable of `exp` is bound, either in `globals` or by a
+// `let` inside it.
+fn is_closed(exp: &Expression, globals: &Globals) -> bool {
+    // Names bound by `let`, each linked to the enclosing ones.
+    let mut bound: Vec<(&str, Option<usize>)> = Vec::new();
+    let mut stack: Vec<(&Expression, Option<usize>)> = vec![(exp, None)];
+    while let Some((exp, scope)) = stack.pop() {
+        match &exp.kind {
+            ExpressionKind::Atom(Atom::Var(id)) => {
+                let mut scope = scope;
+                let is_bound = loop {
+                    match scope {
+                        Some(idx) if bound[idx].0 == id => break true,
+                        Some(idx) => scope = bound[idx].1,
+                        None => break globals.contains_key(id),
+                    }
+                };
+                if !is_bound {
+                    return false;
+                }
+            }
+            ExpressionKind::Atom(_) => {}
+            ExpressionKind::Error => return false,
+            ExpressionKind::Oper { lhs, rhs, .. } => {
+                stack.extend([(&**lhs, scope), (&**rhs, scope)]);
+            }
+            ExpressionKind::If {
+                pred,
+                then,
+                or_else,
+            } => stack.extend([(&**pred, scope), (&**then, scope), (&**or_else, scope)]),
+            ExpressionKind::Let { bindings, exp } => {
+                let mut scope = scope;
+                for binding in bindings {
+                    bound.push((&binding.name, scope));
+                    scope = Some(bound.len() - 1);
+                }
+                stack.extend(bindings.iter().map(|binding| (&*binding.exp, scope)));
+                stack.push((exp, scope));
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Offset of the `n`th occurrence of `pat` in `source`, in chars.
+    fn offset(source: &str, pat: &str, n: usize) -> usize {
+        let (idx, _) = source.match_indices(pat).nth(n).unwrap();
+        source[..idx].chars().count()
+    }
+
+    // Hovered span and value, as printed.
+    fn hover_at(source: &str, offset: usize) -> Option<(Span, String)> {
+        hover(source, offset).map(|(span, val)| (span, val.to_string()))
+    }
+
+    #[test]
+    fn diagnostics_of_deep_nesting() {
+        let depth = 10_000;
+        let source = "(+ 1 ".repeat(depth) + "0" + &")".repeat(depth);
+        assert!(diagnostics(&source).is_empty());
+        let source = "(let (x 1) ".repeat(depth) + "y" + &")".repeat(depth);
+        assert!(matches!(
+            &diagnostics(&source)[..],
+            [Error::UnboundIdentifier(span)] if *span == (11 * depth..11 * depth + 1)
+        ));
+    }
+
+    #[test]
+    fn hover_innermost_closed_expression() {
+        let source = "(define x 2)\n(let (y 3) (+ (* x x) y))";
+        let hover = |pat, n| hover_at(source, offset(source, pat, n));
+        assert_eq!(hover("(* x x)", 0), Some((27..34, "4".into())));
+        assert_eq!(hover("x", 1), Some((30..31, "2".into())));
+        // `y` is bound by the `let` around it.
+        assert_eq!(hover("y", 1), None);
+        assert_eq!(hover("(let", 0), Some((13..38, "7".into())));
+        assert_eq!(hover("\n", 0), None);
+    }
+
+    #[test]
+    fn hover_past_syntax_errors() {
+        let source = "(+ 1 #)\n(* 6 7)";
+        assert_eq!(hover_at(source, 9), Some((8..15, "42".into())));
+    }
+
+    #[test]
+    fn definition_of_let_and_define() {
+        let source = "(define x 1)\n(let (x 2) (let (y x) (+ x y)))\n(* x x)";
+        let definition = |pat, n| definition(source, offset(source, pat, n));
+        // The inner `let` binding of `x` shadows the `define`.
+        assert_eq!(definition("x", 2), Some(19..20));
+        assert_eq!(definition("x", 3), Some(19..20));
+        assert_eq!(definition("y", 1), Some(30..31));
+        assert_eq!(definition("x", 4), Some(8..9));
+        assert_eq!(definition("x", 5), Some(8..9));
+        assert_eq!(definition("let", 0), None);
+    }
+}
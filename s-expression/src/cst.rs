@@ -0,0 +1,1040 @@
+//! Lossless concrete syntax tree.
+//!
+//! Unlike the parser, the tree keeps every char of the source: whitespace as
+//! trivia tokens, bad input as `Invalid` nodes. Printing it gives the source
+//! back, so tools can rewrite part of it and leave the rest as it was.
+//!
+//! Green nodes are immutable, know their length but not their position, and
+//! are shared by the trees made from them by an edit. A [`SyntaxNode`] is a
+//! cursor over them, which knows its parent and position. Edits return a new
+//! green root.
+//!
+//! ```
+//! use s_expression::cst::{self, GreenToken, SyntaxKind};
+//!
+//! let source = "(let (x 1)\n  (+ x   2))  ) ";
+//! assert_eq!(cst::parse(source).to_string(), source);
+//!
+//! // Rename `x`, leaving the layout alone.
+//! let mut root = cst::parse(source);
+//! while let Some(token) = root
+//!     .tokens()
+//!     .find(|token| token.kind() == SyntaxKind::Ident && token.text() == "x")
+//! {
+//!     root = cst::SyntaxNode::new_root(token.replace_with(GreenToken::new(SyntaxKind::Ident, "y")));
+//! }
+//! assert_eq!(root.to_string(), "(let (y 1)\n  (+ y   2))  ) ");
+//! ```
+
+use std::{fmt, iter, sync::Arc};
+
+use super::{
+    lexer::{self, TokenKind},
+    Span,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    // Tokens
+    LParen,
+    RParen,
+    Int,
+    Bool,
+    Ident,
+    Quote,
+    Str,
+    Whitespace,
+    /// Input that is no token
+    Error,
+    // Nodes
+    Root,
+    List,
+    /// An int, bool or identifier
+    Atom,
+    /// A datum the language has no use for, such as a string or a stray `)`
+    Invalid,
+}
+
+impl SyntaxKind {
+    pub fn is_trivia(self) -> bool {
+        self == Self::Whitespace
+    }
+}
+
+/// Lossless tree of `source`.
+pub fn parse(source: &str) -> SyntaxNode {
+    SyntaxNode::new_root(build(source))
+}
+
+// Green tree
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GreenToken {
+    kind: SyntaxKind,
+    text: String,
+    // In chars
+    len: usize,
+}
+
+impl GreenToken {
+    pub fn new(kind: SyntaxKind, text: impl Into<String>) -> Self {
+        let text = text.into();
+        let len = text.chars().count();
+        Self { kind, text, len }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Length in chars.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GreenElement {
+    Node(Arc<GreenNode>),
+    Token(Arc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            Self::Node(node) => node.kind,
+            Self::Token(token) => token.kind,
+        }
+    }
+
+    /// Length in chars.
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Node(node) => node.len,
+            Self::Token(token) => token.len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl From<GreenNode> for GreenElement {
+    fn from(node: GreenNode) -> Self {
+        Self::Node(Arc::new(node))
+    }
+}
+
+impl From<GreenToken> for GreenElement {
+    fn from(token: GreenToken) -> Self {
+        Self::Token(Arc::new(token))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GreenNode {
+    kind: SyntaxKind,
+    children: Vec<GreenElement>,
+    // In chars
+    len: usize,
+    // Whether a `"` under it is never closed
+    open_quote: bool,
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> Self {
+        let len = children.iter().map(GreenElement::len).sum();
+        let open_quote = children.iter().any(|child| match child {
+            GreenElement::Node(node) => node.open_quote,
+            GreenElement::Token(token) => {
+                token.kind == SyntaxKind::Error && token.text.starts_with('"')
+            }
+        });
+        Self {
+            kind,
+            children,
+            len,
+            open_quote,
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+
+    /// Length in chars.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// A copy with the child at `index` replaced.
+    pub fn with_child(&self, index: usize, child: GreenElement) -> Self {
+        let mut children = self.children.clone();
+        children[index] = child;
+        Self::new(self.kind, children)
+    }
+}
+
+// Nested nodes are printed and dropped on an explicit stack, so nesting depth
+// is only limited by the heap.
+
+impl fmt::Display for GreenNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut stack: Vec<&GreenElement> = self.children.iter().rev().collect();
+        while let Some(element) = stack.pop() {
+            match element {
+                GreenElement::Node(node) => stack.extend(node.children.iter().rev()),
+                GreenElement::Token(token) => f.write_str(&token.text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for GreenNode {
+    fn drop(&mut self) {
+        let mut stack: Vec<Arc<GreenNode>> = Vec::new();
+        let mut children = std::mem::take(&mut self.children);
+        loop {
+            stack.extend(children.drain(..).filter_map(|child| match child {
+                GreenElement::Node(node) => Some(node),
+                GreenElement::Token(_) => None,
+            }));
+            let Some(node) = stack.pop() else {
+                return;
+            };
+            // Shared nodes are left to their other owners.
+            if let Ok(mut node) = Arc::try_unwrap(node) {
+                children = std::mem::take(&mut node.children);
+            }
+        }
+    }
+}
+
+// Building
+//
+// The tokens of the lexer cover the source but whitespaces, which fill the
+// gaps between them.
+
+fn build(source: &str) -> GreenNode {
+    // Byte offset of every char, and of the end
+    let offsets: Vec<usize> = source
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain(iter::once(source.len()))
+        .collect();
+    let text = |span: Span| &source[offsets[span.start]..offsets[span.end]];
+    let mut builder = Builder::default();
+    let mut end = 0;
+    for token in lexer::tokenize(source) {
+        builder.gap(text(end..token.span.start));
+        end = token.span.end;
+        let text = text(token.span);
+        match token.kind {
+            TokenKind::LParen => {
+                builder.start();
+                builder.token(SyntaxKind::LParen, text);
+            }
+            TokenKind::RParen if builder.depth() > 0 => {
+                builder.token(SyntaxKind::RParen, text);
+                builder.finish(SyntaxKind::List);
+            }
+            TokenKind::RParen => builder.wrap(SyntaxKind::Invalid, SyntaxKind::RParen, text),
+            TokenKind::Int(_) => builder.wrap(SyntaxKind::Atom, SyntaxKind::Int, text),
+            TokenKind::Bool(_) => builder.wrap(SyntaxKind::Atom, SyntaxKind::Bool, text),
+            TokenKind::Ident(_) => builder.wrap(SyntaxKind::Atom, SyntaxKind::Ident, text),
+            TokenKind::Quote => builder.wrap(SyntaxKind::Invalid, SyntaxKind::Quote, text),
+            TokenKind::Str(_) => builder.wrap(SyntaxKind::Invalid, SyntaxKind::Str, text),
+            TokenKind::Error => builder.wrap(SyntaxKind::Invalid, SyntaxKind::Error, text),
+        }
+    }
+    builder.gap(text(end..offsets.len() - 1));
+    // The open lists are closed at the end of the source.
+    while builder.depth() > 0 {
+        builder.finish(SyntaxKind::List);
+    }
+    GreenNode::new(SyntaxKind::Root, builder.root)
+}
+
+#[derive(Default)]
+struct Builder {
+    root: Vec<GreenElement>,
+    // Children of every node being built
+    stack: Vec<Vec<GreenElement>>,
+}
+
+impl Builder {
+    fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    fn children(&mut self) -> &mut Vec<GreenElement> {
+        self.stack.last_mut().unwrap_or(&mut self.root)
+    }
+
+    fn token(&mut self, kind: SyntaxKind, text: &str) {
+        self.children().push(GreenToken::new(kind, text).into());
+    }
+
+    fn start(&mut self) {
+        self.stack.push(Vec::new());
+    }
+
+    fn finish(&mut self, kind: SyntaxKind) {
+        let children = self.stack.pop().unwrap();
+        self.children().push(GreenNode::new(kind, children).into());
+    }
+
+    fn wrap(&mut self, node: SyntaxKind, token: SyntaxKind, text: &str) {
+        self.start();
+        self.token(token, text);
+        self.finish(node);
+    }
+
+    // Whitespaces only, unless the lexer skipped some input.
+    fn gap(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        if text.chars().all(char::is_whitespace) {
+            self.token(SyntaxKind::Whitespace, text);
+        } else {
+            self.wrap(SyntaxKind::Invalid, SyntaxKind::Error, text);
+        }
+    }
+}
+
+// Incremental reparsing
+
+/// A change of the source: the chars in `span` replaced by `text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub span: Span,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(span: Span, text: impl Into<String>) -> Self {
+        Self {
+            span,
+            text: text.into(),
+        }
+    }
+
+    pub fn apply(&self, source: &str) -> String {
+        splice(source, self.span.clone(), &self.text)
+    }
+}
+
+/// The tree of the source of `root` with `edit` applied, the same as
+/// `parse` would give.
+///
+/// Only the innermost list around the edit is read again, or failing that the
+/// top-level forms it touches. The nodes elsewhere are shared with `root`.
+///
+/// ```
+/// use s_expression::cst::{self, GreenElement, TextEdit};
+///
+/// let root = cst::parse("(define x 1)\n(+ x 2)");
+/// let edit = TextEdit::new(18..19, "40");
+/// let new = cst::reparse(&root, &edit);
+/// assert_eq!(new.to_string(), "(define x 1)\n(+ x 40)");
+///
+/// let (GreenElement::Node(old), GreenElement::Node(first)) =
+///     (&root.green().children()[0], &new.green().children()[0])
+/// else {
+///     panic!()
+/// };
+/// assert!(std::sync::Arc::ptr_eq(old, first));
+/// ```
+pub fn reparse(root: &SyntaxNode, edit: &TextEdit) -> SyntaxNode {
+    // A `"` never closed is read up to the end of the source, where the edit
+    // may close it.
+    let green = (!root.green().open_quote)
+        .then(|| reparse_list(root, edit).or_else(|| reparse_forms(root, edit)))
+        .flatten()
+        .unwrap_or_else(|| build(&edit.apply(&root.to_string())));
+    SyntaxNode::new_root(green)
+}
+
+// Reads the innermost closed list around the edit again. Its parens are left
+// alone, so the tokens around it stay the same.
+//
+// Tokens only look one char past their end, but for a `"`, which looks for
+// the next one. Unless it ends the source, a part read again must not leave
+// one open, since the `"` might be closed further on.
+fn reparse_list(root: &SyntaxNode, edit: &TextEdit) -> Option<GreenNode> {
+    let inside = |node: &SyntaxNode| {
+        let span = node.span();
+        span.start < edit.span.start && edit.span.end < span.end
+    };
+    let mut list = None;
+    let mut node = root.clone();
+    loop {
+        let child = node.children().find(|child| inside(child));
+        let Some(child) = child else {
+            break;
+        };
+        if child.kind() == SyntaxKind::List && List(child.clone()).r_paren().is_some() {
+            list = Some(child.clone());
+        }
+        node = child;
+    }
+    let list = list?;
+    let start = list.span().start;
+    let text = splice(
+        &list.to_string(),
+        edit.span.start - start..edit.span.end - start,
+        &edit.text,
+    );
+    let new = build(&text);
+    // Still one closed list, or the edit reaches out of it.
+    let [GreenElement::Node(new)] = new.children() else {
+        return None;
+    };
+    let closed = new.children().last().map(GreenElement::kind) == Some(SyntaxKind::RParen);
+    if new.kind() != SyntaxKind::List || !closed || new.open_quote {
+        return None;
+    }
+    Some(list.replace_with((**new).clone()))
+}
+
+// Reads the top-level elements touching the edit again, with one more on each
+// side: the token before them cannot run into the edit then, and the one
+// after shows where the new elements end.
+fn reparse_forms(root: &SyntaxNode, edit: &TextEdit) -> Option<GreenNode> {
+    let children = root.green().children();
+    let mut spans = Vec::with_capacity(children.len());
+    let mut offset = 0;
+    for child in children {
+        spans.push(offset..offset + child.len());
+        offset += child.len();
+    }
+    let first = spans.iter().position(|span| span.end >= edit.span.start)?;
+    let last = spans.iter().rposition(|span| span.start <= edit.span.end)?;
+    let first = first.saturating_sub(1);
+    let last = (last + 1).min(children.len() - 1);
+    let start = spans[first].start;
+    let old: String = children[first..=last]
+        .iter()
+        .map(|child| match child {
+            GreenElement::Node(node) => node.to_string(),
+            GreenElement::Token(token) => token.text.clone(),
+        })
+        .collect();
+    let text = splice(
+        &old,
+        edit.span.start - start..edit.span.end - start,
+        &edit.text,
+    );
+    let new = build(&text);
+    // Unless the source ends there, the element after the edit must come out
+    // the same, or the change runs on past it.
+    if last + 1 < children.len() && (new.open_quote || new.children().last() != children.get(last))
+    {
+        return None;
+    }
+    let mut new_children = children[..first].to_vec();
+    new_children.extend(new.children().iter().cloned());
+    new_children.extend(children[last + 1..].iter().cloned());
+    Some(GreenNode::new(SyntaxKind::Root, new_children))
+}
+
+// `text` with the chars in `span` replaced by `with`.
+fn splice(text: &str, span: Span, with: &str) -> String {
+    let offset = |pos: usize| {
+        text.char_indices()
+            .nth(pos)
+            .map_or(text.len(), |(offset, _)| offset)
+    };
+    let (start, end) = (offset(span.start), offset(span.end));
+    [&text[..start], with, &text[end..]].concat()
+}
+
+// Syntax tree
+
+/// A node of a green tree, with its parent and position.
+#[derive(Debug, Clone)]
+pub struct SyntaxNode(Arc<NodeData>);
+
+#[derive(Debug)]
+struct NodeData {
+    green: Arc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    // Index in the children of the parent
+    index: usize,
+    // Char offset in the source
+    offset: usize,
+}
+
+/// A token of a green tree, with its parent and position.
+#[derive(Debug, Clone)]
+pub struct SyntaxToken {
+    green: Arc<GreenToken>,
+    parent: SyntaxNode,
+    index: usize,
+    offset: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: GreenNode) -> Self {
+        Self(Arc::new(NodeData {
+            green: Arc::new(green),
+            parent: None,
+            index: 0,
+            offset: 0,
+        }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind
+    }
+
+    pub fn span(&self) -> Span {
+        self.0.offset..self.0.offset + self.0.green.len
+    }
+
+    pub fn green(&self) -> &GreenNode {
+        &self.0.green
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.clone()
+    }
+
+    /// The next node with the same parent, skipping tokens.
+    pub fn next_sibling(&self) -> Option<SyntaxNode> {
+        let parent = self.parent()?;
+        let sibling = parent
+            .children()
+            .find(|sibling| sibling.0.index > self.0.index);
+        sibling
+    }
+
+    /// The previous node with the same parent, skipping tokens.
+    pub fn prev_sibling(&self) -> Option<SyntaxNode> {
+        let parent = self.parent()?;
+        let sibling = parent
+            .children()
+            .take_while(|sibling| sibling.0.index < self.0.index)
+            .last();
+        sibling
+    }
+
+    pub fn children_with_tokens(&self) -> impl Iterator<Item = SyntaxElement> + '_ {
+        let mut offset = self.0.offset;
+        self.0
+            .green
+            .children
+            .iter()
+            .enumerate()
+            .map(move |(index, child)| {
+                let element = match child {
+                    GreenElement::Node(green) => SyntaxElement::Node(Self(Arc::new(NodeData {
+                        green: green.clone(),
+                        parent: Some(self.clone()),
+                        index,
+                        offset,
+                    }))),
+                    GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                        green: green.clone(),
+                        parent: self.clone(),
+                        index,
+                        offset,
+                    }),
+                };
+                offset += child.len();
+                element
+            })
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = SyntaxNode> + '_ {
+        self.children_with_tokens()
+            .filter_map(|element| match element {
+                SyntaxElement::Node(node) => Some(node),
+                SyntaxElement::Token(_) => None,
+            })
+    }
+
+    /// This node and the nodes and tokens under it, in source order.
+    pub fn descendants_with_tokens(&self) -> impl Iterator<Item = SyntaxElement> {
+        let mut stack = vec![SyntaxElement::Node(self.clone())];
+        iter::from_fn(move || {
+            let element = stack.pop()?;
+            if let SyntaxElement::Node(node) = &element {
+                let children: Vec<_> = node.children_with_tokens().collect();
+                stack.extend(children.into_iter().rev());
+            }
+            Some(element)
+        })
+    }
+
+    /// The tokens under this node, in source order.
+    pub fn tokens(&self) -> impl Iterator<Item = SyntaxToken> {
+        self.descendants_with_tokens()
+            .filter_map(|element| match element {
+                SyntaxElement::Token(token) => Some(token),
+                SyntaxElement::Node(_) => None,
+            })
+    }
+
+    /// The innermost node spanning char `offset`.
+    pub fn covering_node(&self, offset: usize) -> SyntaxNode {
+        let mut node = self.clone();
+        loop {
+            let child = node.children().find(|child| child.span().contains(&offset));
+            match child {
+                Some(child) => node = child,
+                None => return node,
+            }
+        }
+    }
+
+    /// The green root of a tree where this node is replaced by `green`.
+    pub fn replace_with(&self, green: GreenNode) -> GreenNode {
+        let mut green = GreenElement::from(green);
+        let mut node = self.clone();
+        while let Some(parent) = node.parent() {
+            green = parent.green().with_child(node.0.index, green).into();
+            node = parent;
+        }
+        match green {
+            GreenElement::Node(node) => {
+                Arc::try_unwrap(node).unwrap_or_else(|node| (*node).clone())
+            }
+            GreenElement::Token(_) => unreachable!(),
+        }
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.green.fmt(f)
+    }
+}
+
+impl Drop for NodeData {
+    fn drop(&mut self) {
+        // Ancestors no one else holds are dropped in turn.
+        let mut parent = self.parent.take();
+        while let Some(SyntaxNode(node)) = parent {
+            parent = Arc::try_unwrap(node)
+                .ok()
+                .and_then(|mut node| node.parent.take());
+        }
+    }
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> SyntaxKind {
+        self.green.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+
+    pub fn span(&self) -> Span {
+        self.offset..self.offset + self.green.len
+    }
+
+    pub fn green(&self) -> &GreenToken {
+        &self.green
+    }
+
+    pub fn parent(&self) -> &SyntaxNode {
+        &self.parent
+    }
+
+    /// The green root of a tree where this token is replaced by `green`.
+    pub fn replace_with(&self, green: GreenToken) -> GreenNode {
+        let parent = self.parent.green().with_child(self.index, green.into());
+        self.parent.replace_with(parent)
+    }
+}
+
+impl fmt::Display for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.text())
+    }
+}
+
+impl SyntaxElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            Self::Node(node) => node.kind(),
+            Self::Token(token) => token.kind(),
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Node(node) => node.span(),
+            Self::Token(token) => token.span(),
+        }
+    }
+}
+
+// Typed views
+//
+// Each wraps a node of its kind, reading the tree on demand.
+
+pub trait AstNode: Sized {
+    fn cast(node: SyntaxNode) -> Option<Self>;
+
+    fn syntax(&self) -> &SyntaxNode;
+}
+
+#[derive(Debug, Clone)]
+pub struct Root(SyntaxNode);
+
+#[derive(Debug, Clone)]
+pub struct List(SyntaxNode);
+
+#[derive(Debug, Clone)]
+pub struct Atom(SyntaxNode);
+
+/// A list or an atom. `Invalid` nodes have no view.
+#[derive(Debug, Clone)]
+pub enum Sexp {
+    List(List),
+    Atom(Atom),
+}
+
+impl AstNode for Root {
+    fn cast(node: SyntaxNode) -> Option<Self> {
+        (node.kind() == SyntaxKind::Root).then(|| Self(node))
+    }
+
+    fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+}
+
+impl AstNode for List {
+    fn cast(node: SyntaxNode) -> Option<Self> {
+        (node.kind() == SyntaxKind::List).then(|| Self(node))
+    }
+
+    fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+}
+
+impl AstNode for Atom {
+    fn cast(node: SyntaxNode) -> Option<Self> {
+        (node.kind() == SyntaxKind::Atom).then(|| Self(node))
+    }
+
+    fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+}
+
+impl AstNode for Sexp {
+    fn cast(node: SyntaxNode) -> Option<Self> {
+        match node.kind() {
+            SyntaxKind::List => Some(Self::List(List(node))),
+            SyntaxKind::Atom => Some(Self::Atom(Atom(node))),
+            _ => None,
+        }
+    }
+
+    fn syntax(&self) -> &SyntaxNode {
+        match self {
+            Self::List(list) => list.syntax(),
+            Self::Atom(atom) => atom.syntax(),
+        }
+    }
+}
+
+impl Root {
+    /// The top-level forms.
+    pub fn forms(&self) -> impl Iterator<Item = Sexp> + '_ {
+        self.0.children().filter_map(Sexp::cast)
+    }
+}
+
+impl List {
+    pub fn items(&self) -> impl Iterator<Item = Sexp> + '_ {
+        self.0.children().filter_map(Sexp::cast)
+    }
+
+    /// The identifier the list starts with, such as `let` or `+`.
+    pub fn head(&self) -> Option<Atom> {
+        match self.items().next()? {
+            Sexp::Atom(atom) if atom.kind() == SyntaxKind::Ident => Some(atom),
+            _ => None,
+        }
+    }
+
+    pub fn l_paren(&self) -> Option<SyntaxToken> {
+        self.paren(SyntaxKind::LParen)
+    }
+
+    /// `None` if the source ends before it.
+    pub fn r_paren(&self) -> Option<SyntaxToken> {
+        self.paren(SyntaxKind::RParen)
+    }
+
+    fn paren(&self, kind: SyntaxKind) -> Option<SyntaxToken> {
+        self.0
+            .children_with_tokens()
+            .find_map(|element| match element {
+                SyntaxElement::Token(token) if token.kind() == kind => Some(token),
+                _ => None,
+            })
+    }
+}
+
+impl Atom {
+    pub fn token(&self) -> SyntaxToken {
+        self.0.tokens().next().unwrap()
+    }
+
+    /// `Int`, `Bool` or `Ident`.
+    pub fn kind(&self) -> SyntaxKind {
+        self.token().kind()
+    }
+
+    pub fn text(&self) -> String {
+        self.token().text().to_owned()
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        let token = self.token();
+        (token.kind() == SyntaxKind::Int)
+            .then(|| token.text().parse().ok())
+            .flatten()
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        let token = self.token();
+        (token.kind() == SyntaxKind::Bool).then(|| token.text() == "true")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(node: &SyntaxNode) -> Vec<SyntaxKind> {
+        node.children_with_tokens()
+            .map(|element| element.kind())
+            .collect()
+    }
+
+    #[test]
+    fn lossless() {
+        for source in [
+            "",
+            "   \n\t ",
+            "(let (x 1)\n  (+ x   2))",
+            // No comments in the language, read as bad tokens
+            "; a comment\n(f 1) ; another\n",
+            "(f #x 1x) )) \"a\\\"b\" 'c (",
+            "𝕏 (é \r\n 1)",
+        ] {
+            let root = parse(source);
+            assert_eq!(root.to_string(), source);
+            let tokens: String = root.tokens().map(|token| token.to_string()).collect();
+            assert_eq!(tokens, source);
+            assert_eq!(root.span(), 0..source.chars().count());
+        }
+    }
+
+    #[test]
+    fn trivia_and_error_tokens() {
+        let root = parse(" (f #) ) ");
+        use SyntaxKind::*;
+        assert_eq!(
+            kinds(&root),
+            [Whitespace, List, Whitespace, Invalid, Whitespace]
+        );
+        let list = root.children().next().unwrap();
+        assert_eq!(kinds(&list), [LParen, Atom, Whitespace, Invalid, RParen]);
+        let bad = list.children().nth(1).unwrap();
+        assert_eq!((kinds(&bad), bad.span()), (vec![Error], 4..5));
+        let stray = root.children().nth(1).unwrap();
+        assert_eq!((kinds(&stray), stray.span()), (vec![RParen], 7..8));
+    }
+
+    #[test]
+    fn navigation() {
+        let root = parse("(a (b c) d) e");
+        assert!(root.parent().is_none());
+        let node = root.covering_node(4);
+        assert_eq!((node.kind(), node.span()), (SyntaxKind::Atom, 4..5));
+        let list = node.parent().unwrap();
+        assert_eq!(list.to_string(), "(b c)");
+        assert_eq!(list.parent().unwrap().to_string(), "(a (b c) d)");
+        assert_eq!(node.next_sibling().unwrap().to_string(), "c");
+        assert!(node.prev_sibling().is_none());
+        assert_eq!(list.prev_sibling().unwrap().to_string(), "a");
+        assert_eq!(list.next_sibling().unwrap().to_string(), "d");
+        let top = list.parent().unwrap();
+        assert_eq!(top.next_sibling().unwrap().to_string(), "e");
+        assert!(top.prev_sibling().is_none());
+        let token = root.tokens().find(|token| token.text() == "d").unwrap();
+        assert_eq!((token.span(), token.parent().span()), (9..10, 9..10));
+        let spans: Vec<_> = root
+            .descendants_with_tokens()
+            .map(|element| element.span())
+            .take(4)
+            .collect();
+        assert_eq!(spans, [0..13, 0..11, 0..1, 1..2]);
+    }
+
+    #[test]
+    fn typed_views() {
+        let root = Root::cast(parse("(let (x 1) x) 42 true \"s\"")).unwrap();
+        assert!(List::cast(root.syntax().clone()).is_none());
+        let forms: Vec<_> = root.forms().collect();
+        // The string has no view.
+        assert_eq!(forms.len(), 3);
+        let Sexp::List(list) = &forms[0] else {
+            panic!("expect list");
+        };
+        assert_eq!(list.head().unwrap().text(), "let");
+        assert_eq!(list.items().count(), 3);
+        assert_eq!(list.l_paren().unwrap().span(), 0..1);
+        assert_eq!(list.r_paren().unwrap().span(), 12..13);
+        let Sexp::Atom(int) = &forms[1] else {
+            panic!("expect atom");
+        };
+        assert_eq!(
+            (int.kind(), int.as_int(), int.as_bool()),
+            (SyntaxKind::Int, Some(42), None)
+        );
+        let Sexp::Atom(bool) = &forms[2] else {
+            panic!("expect atom");
+        };
+        assert_eq!((bool.as_int(), bool.as_bool()), (None, Some(true)));
+        assert!(Atom::cast(list.syntax().clone()).is_none());
+        assert!(Sexp::cast(root.syntax().clone()).is_none());
+
+        let unclosed = Root::cast(parse("((1) (")).unwrap();
+        let Some(Sexp::List(list)) = unclosed.forms().next() else {
+            panic!("expect list");
+        };
+        assert!(list.r_paren().is_none());
+        assert!(list.head().is_none());
+    }
+
+    // Edits `source` one after another, checking the tree reparsed after
+    // each against the one parsed from scratch.
+    fn check_edits(source: &str, edits: impl IntoIterator<Item = TextEdit>) {
+        let mut source = source.to_owned();
+        let mut root = parse(&source);
+        for edit in edits {
+            let before = source.clone();
+            source = edit.apply(&source);
+            root = reparse(&root, &edit);
+            assert_eq!(
+                root.green(),
+                parse(&source).green(),
+                "{:?} edited by {:?}",
+                before,
+                edit
+            );
+        }
+    }
+
+    #[test]
+    fn reparse_edge_cases() {
+        let source = "(let (x 1) (if (< x 2) (+ x 1) x)) (define y (* 2 3))";
+        let len = source.chars().count();
+        for edit in [
+            // At offset 0 and at the end
+            TextEdit::new(0..0, "("),
+            TextEdit::new(0..1, ""),
+            TextEdit::new(0..0, "x "),
+            TextEdit::new(len..len, ")"),
+            TextEdit::new(len..len, " (f"),
+            TextEdit::new(len - 1..len, ""),
+            TextEdit::new(0..len, ""),
+            // Across list boundaries
+            TextEdit::new(8..13, ""),
+            TextEdit::new(9..16, ") (if"),
+            TextEdit::new(33..38, "y"),
+            TextEdit::new(4..40, "(x"),
+            // Unbalancing parens
+            TextEdit::new(5..6, ""),
+            TextEdit::new(9..10, ""),
+            TextEdit::new(22..22, ")"),
+            TextEdit::new(22..22, "(("),
+            TextEdit::new(34..35, ""),
+            // Strings, which run on to the next `"`
+            TextEdit::new(11..11, "\""),
+            TextEdit::new(40..40, "\""),
+        ] {
+            check_edits(source, [edit]);
+        }
+    }
+
+    #[test]
+    fn reparse_random_edits() {
+        let pieces = [
+            "(", ")", " ", "\n", "x", "1", "ab", "\"", "#", "(+ 1 2)", "",
+        ];
+        for seed in [
+            0x2545_f491_4f6c_dd1d,
+            1,
+            42,
+            0xdead_beef,
+            0x9e37_79b9_7f4a_7c15,
+        ] {
+            let mut state: u64 = seed;
+            let mut random = |n: usize| {
+                // xorshift64
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state % n as u64) as usize
+            };
+            let mut source = String::from("(let (x 1) (if (< x 2) (+ x 1) x)) (define y (* 2 3))");
+            let mut root = parse(&source);
+            for step in 0..1000 {
+                let len = source.chars().count();
+                let start = random(len + 1);
+                let end = start + random(len - start + 1).min(3);
+                let edit = TextEdit::new(start..end, pieces[random(pieces.len())]);
+                let before = source.clone();
+                source = edit.apply(&source);
+                root = reparse(&root, &edit);
+                assert_eq!(
+                    root.green(),
+                    parse(&source).green(),
+                    "seed {:#x}, step {}: {:?} edited by {:?}",
+                    seed,
+                    step,
+                    before,
+                    edit
+                );
+            }
+        }
+    }
+}
@@ -1,38 +1,238 @@
+use std::{rc::Rc, time::Instant};
+
 use super::{
-    expression::{Atom, Env, Expression, Operator},
-    Error, Result, Val,
+    expression::{Atom, Expression, ExpressionKind, Globals, Operator},
+    Error, Limits, Result, Val,
 };
 
-pub(super) fn eval(exp: Expression) -> Result<Val> {
-    exp.eval(&Env::new())
+pub(super) fn eval(exp: &Expression, limits: &Limits) -> Result<Val> {
+    eval_with_globals(exp, &Globals::new(), limits)
+}
+
+pub(super) fn eval_with_globals(
+    exp: &Expression,
+    globals: &Globals,
+    limits: &Limits,
+) -> Result<Val> {
+    Machine {
+        globals,
+        budget: Budget::new(limits),
+        stack: Vec::new(),
+    }
+    .run(exp)
 }
 
-impl Expression {
-    fn eval(&self, env: &Env) -> Result<Val> {
-        match self {
-            Self::Atom(atom) => eval_atom(atom, env),
-            Self::Oper { oper, lhs, rhs } => eval_oper(oper, env, lhs, rhs),
-            Self::If {
+// Machine
+//
+// The control is either an expression to evaluate in an environment or a
+// value to return, and the continuation is an explicit stack of frames
+// waiting for that value. `if` branches, `let` bodies and variables replace
+// the control without pushing a frame, so they are tail calls.
+//
+// `let` binds names to expressions rather than values: a variable evaluates
+// its bound expression where it is used, in the environment there.
+
+struct Machine<'a> {
+    globals: &'a Globals,
+    budget: Budget<'a>,
+    stack: Vec<Frame<'a>>,
+}
+
+enum Control<'a> {
+    Eval(&'a Expression, Env<'a>),
+    Return(Val),
+}
+
+enum Frame<'a> {
+    // Waiting for the left operand
+    OperLhs {
+        exp: &'a Expression,
+        oper: Operator,
+        lhs: &'a Expression,
+        rhs: &'a Expression,
+        env: Env<'a>,
+    },
+    // Waiting for the right operand
+    OperRhs {
+        exp: &'a Expression,
+        oper: Operator,
+        lhs: &'a Expression,
+        lhs_val: Val,
+        rhs: &'a Expression,
+    },
+    // Waiting for the predicate
+    If {
+        pred: &'a Expression,
+        then: &'a Expression,
+        or_else: &'a Expression,
+        env: Env<'a>,
+    },
+}
+
+impl<'a> Machine<'a> {
+    fn run(mut self, exp: &'a Expression) -> Result<Val> {
+        let mut control = Control::Eval(exp, Env::default());
+        loop {
+            control = match control {
+                Control::Eval(exp, env) => self.eval(exp, env)?,
+                Control::Return(val) => match self.stack.pop() {
+                    Some(frame) => self.resume(frame, val)?,
+                    None => return Ok(val),
+                },
+            };
+        }
+    }
+
+    fn eval(&mut self, exp: &'a Expression, env: Env<'a>) -> Result<Control<'a>> {
+        self.budget.step(exp, self.stack.len())?;
+        let control = match &exp.kind {
+            ExpressionKind::Atom(atom) => self.eval_atom(atom, env, exp)?,
+            // Only reached when evaluating a partial parse.
+            ExpressionKind::Error => {
+                let err = parsec::Error::new(exp.span.start, "syntax error");
+                return Err(Error::Parser(err));
+            }
+            ExpressionKind::Oper { oper, lhs, rhs } => {
+                self.stack.push(Frame::OperLhs {
+                    exp,
+                    oper: *oper,
+                    lhs,
+                    rhs,
+                    env: env.clone(),
+                });
+                Control::Eval(lhs, env)
+            }
+            ExpressionKind::If {
+                pred,
+                then,
+                or_else,
+            } => {
+                self.stack.push(Frame::If {
+                    pred,
+                    then,
+                    or_else,
+                    env: env.clone(),
+                });
+                Control::Eval(pred, env)
+            }
+            ExpressionKind::Let {
+                bindings,
+                exp: body,
+            } => {
+                let mut env = env;
+                for binding in bindings {
+                    self.budget.alloc(exp)?;
+                    env = env.bind(&binding.name, &binding.exp);
+                }
+                Control::Eval(body, env)
+            }
+        };
+        Ok(control)
+    }
+
+    fn resume(&mut self, frame: Frame<'a>, val: Val) -> Result<Control<'a>> {
+        let control = match frame {
+            Frame::OperLhs {
+                exp,
+                oper,
+                lhs,
+                rhs,
+                env,
+            } => {
+                self.stack.push(Frame::OperRhs {
+                    exp,
+                    oper,
+                    lhs,
+                    lhs_val: val,
+                    rhs,
+                });
+                Control::Eval(rhs, env)
+            }
+            Frame::OperRhs {
+                exp,
+                oper,
+                lhs,
+                lhs_val,
+                rhs,
+            } => Control::Return(eval_oper(oper, exp, (lhs, lhs_val), (rhs, val))?),
+            Frame::If {
                 pred,
                 then,
                 or_else,
-            } => eval_if(env, pred, then, or_else),
-            Self::Let { env: self_env, exp } => eval_let(env, self_env, exp),
-        }
+                env,
+            } => match val {
+                Val::Bool(true) => Control::Eval(then, env),
+                Val::Bool(false) => Control::Eval(or_else, env),
+                _ => return Err(Error::TypeMismatch(pred.span.clone())),
+            },
+        };
+        Ok(control)
+    }
+
+    fn eval_atom(&self, atom: &Atom, env: Env<'a>, exp: &Expression) -> Result<Control<'a>> {
+        let val = match atom {
+            Atom::Int(val) => Val::Int(*val),
+            Atom::Bool(val) => Val::Bool(*val),
+            Atom::Var(id) => match env.get(id) {
+                Some(bound) => return Ok(Control::Eval(bound, env)),
+                None => match self.globals.get(id) {
+                    Some(val) => *val,
+                    None => return Err(Error::UnboundIdentifier(exp.span.clone())),
+                },
+            },
+        };
+        Ok(Control::Return(val))
     }
 }
 
-// Atom
+// Env
+//
+// Expressions bound by `let`, innermost first. The last binding of a name
+// in a `let` hides the ones before it.
+
+#[derive(Clone, Default)]
+struct Env<'a> {
+    head: Option<Rc<EnvNode<'a>>>,
+}
 
-fn eval_atom(atom: &Atom, env: &Env) -> Result<Val> {
-    match atom {
-        Atom::Int(val) => Ok(Val::Int(*val)),
-        Atom::Bool(val) => Ok(Val::Bool(*val)),
-        Atom::Var(id) => {
-            let Some(exp) = env.get(id) else {
-                return Err(Error::UnboundIdentifier);
+struct EnvNode<'a> {
+    name: &'a str,
+    exp: &'a Expression,
+    parent: Env<'a>,
+}
+
+impl<'a> Env<'a> {
+    fn bind(self, name: &'a str, exp: &'a Expression) -> Self {
+        Self {
+            head: Some(Rc::new(EnvNode {
+                name,
+                exp,
+                parent: self,
+            })),
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&'a Expression> {
+        let mut node = self.head.as_ref();
+        while let Some(current) = node {
+            if current.name == name {
+                return Some(current.exp);
+            }
+            node = current.parent.head.as_ref();
+        }
+        None
+    }
+}
+
+// Long chains are dropped iteratively, deep nesting would overflow the stack.
+impl Drop for Env<'_> {
+    fn drop(&mut self) {
+        let mut head = self.head.take();
+        while let Some(node) = head {
+            head = match Rc::try_unwrap(node) {
+                Ok(mut node) => node.parent.head.take(),
+                Err(_) => None,
             };
-            return exp.eval(env);
         }
     }
 }
@@ -40,53 +240,139 @@ fn eval_atom(atom: &Atom, env: &Env) -> Result<Val> {
 // Operator
 
 fn eval_oper(
-    operator: &Operator,
-    env: &Env,
-    lhs: &Box<Expression>,
-    rhs: &Box<Expression>,
+    operator: Operator,
+    exp: &Expression,
+    (lhs, lhs_val): (&Expression, Val),
+    (rhs, rhs_val): (&Expression, Val),
 ) -> Result<Val> {
-    let (Val::Int(lhs), Val::Int(rhs)) = (lhs.eval(env)?, rhs.eval(env)?) else {
-        return Err(Error::TypeMismatch)
+    let (lhs, rhs) = match (lhs_val, rhs_val) {
+        (Val::Int(lhs), Val::Int(rhs)) => (lhs, rhs),
+        (Val::Int(_), _) => return Err(Error::TypeMismatch(rhs.span.clone())),
+        _ => return Err(Error::TypeMismatch(lhs.span.clone())),
     };
-    let val = match operator {
-        Operator::Add => (lhs + rhs).into(),
-        Operator::Subtract => (lhs - rhs).into(),
-        Operator::Multiply => (lhs * rhs).into(),
+    let int = match operator {
+        Operator::Add => lhs.checked_add(rhs),
+        Operator::Subtract => lhs.checked_sub(rhs),
+        Operator::Multiply => lhs.checked_mul(rhs),
         Operator::Divide => {
             if rhs == 0 {
-                return Err(Error::DivisionByZero);
+                return Err(Error::DivisionByZero(exp.span.clone()));
             }
-            (lhs / rhs).into()
+            // Only `i64::MIN / -1` overflows.
+            lhs.checked_div(rhs)
         }
-        Operator::Eq => (lhs == rhs).into(),
-        Operator::Lt => (lhs < rhs).into(),
-        Operator::Gt => (lhs > rhs).into(),
+        Operator::Eq => return Ok((lhs == rhs).into()),
+        Operator::Lt => return Ok((lhs < rhs).into()),
+        Operator::Gt => return Ok((lhs > rhs).into()),
     };
-    Ok(val)
+    int.map(Val::Int)
+        .ok_or_else(|| Error::Overflow(exp.span.clone()))
 }
 
-// If
+// Budget
 
-fn eval_if(
-    env: &Env,
-    pred: &Box<Expression>,
-    then: &Box<Expression>,
-    or_else: &Box<Expression>,
-) -> Result<Val> {
-    let Val::Bool(bool) = pred.eval(env)? else {
-            return Err(Error::TypeMismatch)
-        };
-    if bool {
-        then.eval(env)
-    } else {
-        or_else.eval(env)
+// How often the deadline is checked, in steps.
+const DEADLINE_INTERVAL: u64 = 1024;
+
+struct Budget<'a> {
+    limits: &'a Limits,
+    deadline: Option<Instant>,
+    steps: u64,
+    values: u64,
+}
+
+impl<'a> Budget<'a> {
+    fn new(limits: &'a Limits) -> Self {
+        Self {
+            limits,
+            deadline: limits.timeout.map(|timeout| Instant::now() + timeout),
+            steps: 0,
+            values: 0,
+        }
+    }
+
+    fn step(&mut self, exp: &Expression, depth: usize) -> Result<()> {
+        self.steps += 1;
+        let limits = self.limits;
+        if let Some(token) = &limits.cancellation {
+            if token.is_cancelled() {
+                return Err(Error::Interrupted(exp.span.clone()));
+            }
+        }
+        if limits.max_steps.is_some_and(|max| self.steps > max) {
+            return Err(Error::StepLimitExceeded(exp.span.clone()));
+        }
+        if limits.max_depth.is_some_and(|max| depth >= max) {
+            return Err(Error::DepthLimitExceeded(exp.span.clone()));
+        }
+        if self.steps.is_multiple_of(DEADLINE_INTERVAL)
+            && self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(Error::Timeout(exp.span.clone()));
+        }
+        Ok(())
+    }
+
+    fn alloc(&mut self, exp: &Expression) -> Result<()> {
+        self.values += 1;
+        if self.limits.max_values.is_some_and(|max| self.values > max) {
+            return Err(Error::ValueLimitExceeded(exp.span.clone()));
+        }
+        Ok(())
     }
 }
 
-// Let
+#[cfg(test)]
+mod tests {
+    use crate::{run, run_with_limits, Error, Limits, Val};
+
+    #[test]
+    fn overflow() {
+        for (source, span) in [
+            ("(* 9999999999 9999999999)", 0..25),
+            ("(+ 9223372036854775807 1)", 0..25),
+            ("(- 0 (- (- 0 9223372036854775807) 2))", 5..36),
+            ("(/ (- (- 0 9223372036854775807) 1) (- 0 1))", 0..43),
+        ] {
+            match run(source) {
+                Err(Error::Overflow(err_span)) => assert_eq!(err_span, span, "{}", source),
+                res => panic!("{}: expected overflow, got {:?}", source, res),
+            }
+        }
+        assert!(run("(* 3037000499 3037000499)").is_ok());
+        assert!(matches!(run("(/ 1 0)"), Err(Error::DivisionByZero(_))));
+    }
+
+    // `let` binds expressions, evaluated where the variable is used.
+    #[test]
+    fn let_is_lazy_and_dynamically_scoped() {
+        assert!(matches!(run("(let (x (/ 1 0)) 5)"), Ok(Val::Int(5))));
+        assert!(matches!(
+            run("(let (x 1) (let (y x) (let (x 2) y)))"),
+            Ok(Val::Int(2))
+        ));
+        assert!(matches!(
+            run("(let (x 1) (x 2) (let (y (+ x 10)) y))"),
+            Ok(Val::Int(12))
+        ));
+        // A binding sees the later ones of its `let` too.
+        assert!(matches!(run("(let (y x) (x 3) y)"), Ok(Val::Int(3))));
+        // Refers to itself forever, stopped by the limits only.
+        let limits = Limits::new().max_steps(1_000);
+        assert!(matches!(
+            run_with_limits("(let (x x) x)", &limits),
+            Err(Error::StepLimitExceeded(_))
+        ));
+    }
 
-fn eval_let(parent_env: &Env, self_env: &Env, exp: &Box<Expression>) -> Result<Val> {
-    let mut env = parent_env.clone();
-    env.extend(self_env.clone());
-    exp.eval(&env)
+    #[test]
+    fn deep_nesting() {
+        let depth = 10_000;
+        let source = "(let (x 1) ".repeat(depth) + "x" + &")".repeat(depth);
+        assert!(matches!(run(&source), Ok(Val::Int(1))));
+        let source = "(+ 1 ".repeat(depth) + "0" + &")".repeat(depth);
+        assert!(matches!(run(&source), Ok(Val::Int(10_000))));
+    }
 }
@@ -1,9 +1,25 @@
 use std::collections::HashMap;
 
-pub(super) type Env = HashMap<String, Box<Expression>>;
+use super::{Span, Val};
+
+/// Top-level definitions of a session.
+pub(super) type Globals = HashMap<String, Val>;
+
+/// A top-level form.
+#[derive(Debug, Clone)]
+pub(super) enum Form {
+    Define(Binding),
+    Exp(Expression),
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Expression {
+    pub(super) kind: ExpressionKind,
+    pub(super) span: Span,
+}
 
 #[derive(Debug, Clone)]
-pub(super) enum Expression {
+pub(super) enum ExpressionKind {
     Atom(Atom),
     Oper {
         oper: Operator,
@@ -16,9 +32,48 @@ pub(super) enum Expression {
         or_else: Box<Expression>,
     },
     Let {
-        env: Env,
+        bindings: Vec<Binding>,
         exp: Box<Expression>,
     },
+    // Placeholder of a malformed expression
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Binding {
+    pub(super) name: String,
+    pub(super) name_span: Span,
+    pub(super) exp: Box<Expression>,
+}
+
+// Children are dropped iteratively, deep nesting would overflow the stack.
+impl Drop for Expression {
+    fn drop(&mut self) {
+        let mut stack = Vec::new();
+        take_children(&mut self.kind, &mut stack);
+        while let Some(mut exp) = stack.pop() {
+            take_children(&mut exp.kind, &mut stack);
+        }
+    }
+}
+
+fn take_children(kind: &mut ExpressionKind, stack: &mut Vec<Expression>) {
+    if let ExpressionKind::Atom(_) | ExpressionKind::Error = kind {
+        return;
+    }
+    match std::mem::replace(kind, ExpressionKind::Atom(Atom::Int(0))) {
+        ExpressionKind::Atom(_) | ExpressionKind::Error => {}
+        ExpressionKind::Oper { lhs, rhs, .. } => stack.extend([*lhs, *rhs]),
+        ExpressionKind::If {
+            pred,
+            then,
+            or_else,
+        } => stack.extend([*pred, *then, *or_else]),
+        ExpressionKind::Let { bindings, exp } => {
+            stack.extend(bindings.into_iter().map(|binding| *binding.exp));
+            stack.push(*exp);
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -40,6 +95,15 @@ impl From<bool> for Atom {
     }
 }
 
+impl From<Val> for Atom {
+    fn from(val: Val) -> Self {
+        match val {
+            Val::Int(int) => Self::Int(int),
+            Val::Bool(bool) => Self::Bool(bool),
+        }
+    }
+}
+
 impl From<String> for Atom {
     fn from(string: String) -> Self {
         Self::Var(string)
@@ -0,0 +1,273 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use futures_core::Stream;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use super::{FormReader, FormStatus, ParsedForm};
+
+// Bytes read from the `AsyncRead` at a time
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// FormStream
+///
+/// Top-level forms read from an `AsyncRead`, such as a TCP connection, each
+/// parsed and yielded once complete.
+///
+/// Framing errors end the stream: I/O errors, input that is not UTF-8, and
+/// forms longer than [`FormStream::max_form_len`], reported as
+/// `io::ErrorKind::InvalidData`. Syntax errors are reported per form by
+/// [`ParsedForm::errors`] and the stream goes on, so a form cut off by the
+/// end of the input is yielded with a missing `)`.
+///
+/// ```
+/// use s_expression::{FormStream, Session};
+/// use tokio::io::AsyncWriteExt;
+/// use tokio_stream::StreamExt;
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let (mut client, server) = tokio::io::duplex(64);
+/// tokio::spawn(async move {
+///     client.write_all(b"(define x 2) (* x\n").await.unwrap();
+///     client.write_all(b" 21)").await.unwrap();
+/// });
+///
+/// let mut session = Session::new();
+/// let mut forms = FormStream::new(server);
+/// let mut values = Vec::new();
+/// while let Some(form) = forms.next().await {
+///     values.push(session.execute_form(&form.unwrap()).unwrap());
+/// }
+/// assert_eq!(format!("{:?}", values), "[None, Some(Int(42))]");
+///
+/// // A syntax error is one form's, framing errors end the stream.
+/// let (mut client, server) = tokio::io::duplex(64);
+/// client.write_all(b"(if 1) (+ 1 2) (+ \xff)").await.unwrap();
+/// drop(client);
+/// let mut forms = FormStream::new(server);
+/// let form = forms.next().await.unwrap().unwrap();
+/// assert_eq!(form.errors()[0].to_string(), "[5] if expression syntax error");
+/// let form = forms.next().await.unwrap().unwrap();
+/// assert!(form.errors().is_empty());
+/// assert_eq!(session.execute_form(&form).unwrap().unwrap().to_string(), "3");
+/// let err = forms.next().await.unwrap().unwrap_err();
+/// assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+/// assert!(forms.next().await.is_none());
+///
+/// let (mut client, server) = tokio::io::duplex(64);
+/// client.write_all(b"(+ 1 (+ 2 (+ 3").await.unwrap();
+/// let mut forms = FormStream::new(server).max_form_len(8);
+/// let err = forms.next().await.unwrap().unwrap_err();
+/// assert_eq!(err.to_string(), "form too long");
+/// # });
+/// ```
+pub struct FormStream<R> {
+    io: R,
+    reader: FormReader,
+    max_form_len: Option<usize>,
+    // Framing error, reported after the forms read before it
+    error: Option<io::Error>,
+    done: bool,
+}
+
+impl<R> FormStream<R>
+where
+    R: AsyncRead + Unpin,
+{
+    pub fn new(io: R) -> Self {
+        Self {
+            io,
+            reader: FormReader::new(),
+            max_form_len: None,
+            error: None,
+            done: false,
+        }
+    }
+
+    /// Maximum length in bytes of a form being read, so a peer cannot make
+    /// it buffer without end.
+    pub fn max_form_len(mut self, len: usize) -> Self {
+        self.max_form_len = Some(len);
+        self
+    }
+
+    pub fn into_inner(self) -> R {
+        self.io
+    }
+
+    // Feeds the next chunk of `io` to the reader, finishing it at the end.
+    fn poll_fill(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut chunk = [0; CHUNK_SIZE];
+        let mut buf = ReadBuf::new(&mut chunk);
+        ready!(Pin::new(&mut self.io).poll_read(cx, &mut buf))?;
+        // An empty chunk finishes the reader.
+        self.reader.read_from(&mut buf.filled())?;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R> Stream for FormStream<R>
+where
+    R: AsyncRead + Unpin,
+{
+    type Item = io::Result<ParsedForm>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        while !this.done {
+            match this.reader.next_form() {
+                FormStatus::Form(form) => return Poll::Ready(Some(Ok(form.parse()))),
+                FormStatus::End => this.done = true,
+                FormStatus::Failed(err) => {
+                    this.done = true;
+                    let err = io::Error::new(io::ErrorKind::InvalidData, err.to_string());
+                    return Poll::Ready(Some(Err(err)));
+                }
+                FormStatus::Incomplete => {
+                    if let Some(max) = this.max_form_len {
+                        if this.reader.pending_len() > max {
+                            this.error =
+                                Some(io::Error::new(io::ErrorKind::InvalidData, "form too long"));
+                        }
+                    }
+                    if this.error.is_none() {
+                        if let Err(err) = ready!(this.poll_fill(cx)) {
+                            // The forms read before it come first.
+                            this.error = Some(err);
+                        }
+                        continue;
+                    }
+                    this.done = true;
+                    return Poll::Ready(this.error.take().map(Err));
+                }
+            }
+        }
+        Poll::Ready(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{duplex, AsyncWriteExt};
+    use tokio_stream::StreamExt;
+
+    use super::*;
+    use crate::Session;
+
+    // Writes `writes` one by one through a pipe buffering `buf_size` bytes,
+    // then closes it, and reads the forms. Writing stops where the stream
+    // ends early and drops the pipe.
+    async fn forms(
+        buf_size: usize,
+        writes: &[&str],
+        max_form_len: Option<usize>,
+    ) -> Vec<io::Result<String>> {
+        let (mut client, server) = duplex(buf_size);
+        let writes: Vec<Vec<u8>> = writes
+            .iter()
+            .map(|write| write.as_bytes().to_vec())
+            .collect();
+        let writer = tokio::spawn(async move {
+            for write in writes {
+                if client.write_all(&write).await.is_err() {
+                    break;
+                }
+                tokio::task::yield_now().await;
+            }
+        });
+        let mut forms = FormStream::new(server);
+        if let Some(len) = max_form_len {
+            forms = forms.max_form_len(len);
+        }
+        let forms = forms
+            .map(|form| form.map(|form| form.source.text))
+            .collect()
+            .await;
+        writer.await.unwrap();
+        forms
+    }
+
+    fn texts(forms: Vec<io::Result<String>>) -> Vec<String> {
+        forms.into_iter().map(Result::unwrap).collect()
+    }
+
+    #[tokio::test]
+    async fn char_split_across_reads() {
+        // One byte at a time, so every char of more than one is split.
+        let forms = forms(1, &["(f \"日本\" 'é) 𝕏"], None).await;
+        assert_eq!(texts(forms), ["(f \"日本\" 'é)", "𝕏"]);
+    }
+
+    #[tokio::test]
+    async fn form_split_across_writes() {
+        let writes = [
+            "(def", "ine x", "\n  ", "(+ 1", " 2", ")", ")\n", "(* x", " x)",
+        ];
+        let forms = forms(4, &writes, None).await;
+        assert_eq!(texts(forms), ["(define x\n  (+ 1 2))", "(* x x)"]);
+    }
+
+    #[tokio::test]
+    async fn forms_in_one_write() {
+        let forms = forms(64, &["1 (+ 1 2)\n(f)x  #\n"], None).await;
+        assert_eq!(texts(forms), ["1", "(+ 1 2)", "(f)", "x", "#"]);
+    }
+
+    #[tokio::test]
+    async fn form_too_long() {
+        let forms = forms(4, &["(+ 1 2) (+ 1 (+ 2 (+ 3 4)))"], Some(8)).await;
+        let [first, err]: [_; 2] = forms.try_into().unwrap();
+        assert_eq!(first.unwrap(), "(+ 1 2)");
+        let err = err.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "form too long");
+    }
+
+    #[tokio::test]
+    async fn eof_mid_form() {
+        let forms = forms(4, &["(+ 1 2) (+ 1"], None).await;
+        assert_eq!(texts(forms), ["(+ 1 2)", "(+ 1"]);
+    }
+
+    #[tokio::test]
+    async fn syntax_errors_per_form() {
+        let (mut client, server) = duplex(64);
+        client
+            .write_all("(let (x 1) y) (if)\n(+ é 1) (* 2".as_bytes())
+            .await
+            .unwrap();
+        drop(client);
+        let forms: Vec<_> = FormStream::new(server)
+            .map(|form| form.unwrap())
+            .collect()
+            .await;
+        let errors: Vec<Vec<_>> = forms
+            .iter()
+            .map(|form| {
+                form.errors()
+                    .iter()
+                    .map(|err| (err.span().start, err.to_string()))
+                    .collect()
+            })
+            .collect();
+        let error = |pos: usize, msg: &str| (pos, format!("[{}] {}", pos, msg));
+        assert_eq!(
+            errors,
+            [
+                vec![],
+                vec![error(17, "if expression syntax error")],
+                vec![error(22, "syntax error")],
+                vec![
+                    error(31, "expect ')'"),
+                    error(31, "operator expression syntax error")
+                ],
+            ]
+        );
+        // Evaluation errors are at offsets of the whole input too.
+        let err = Session::new().execute_form(&forms[0]).unwrap_err();
+        assert_eq!((err.kind(), err.span()), ("UnboundIdentifier", 11..12));
+    }
+}
@@ -0,0 +1,263 @@
+use parsec::{consumers::*, expr::expr, recursive, Context, Parser, Recursive, StrStream};
+use parsec_macros::{choice, seq};
+
+use super::{
+    expression::{Atom, Binding, Expression, ExpressionKind, Operator},
+    lexer::spaces,
+    Span,
+};
+
+// Infix notation for the same expressions:
+//
+//   let x = 2, y = 2 + 3 in x * y
+//   if x < 0 then -x else x
+//
+// Comparisons bind loosest, then `+` and `-`, then `*` and `/`, all left
+// associative. A prefix `-x` stands for `(- 0 x)`.
+
+const KEYWORDS: [&str; 7] = ["else", "false", "if", "in", "let", "then", "true"];
+
+pub(super) fn parse(input: &str) -> parsec::Result<Expression> {
+    Context::from_str(input).parse_by(spaces().use_right(exp()).use_left(eos()))
+}
+
+type Exp<'a> = Recursive<'a, StrStream<'a>, Expression>;
+
+fn exp<'a>() -> Exp<'a> {
+    recursive(|exp| {
+        expr(term(exp))
+            .prefix(symbol('-').spanned(), 3, |(_, span), exp| {
+                let zero = Expression {
+                    kind: ExpressionKind::Atom(Atom::Int(0)),
+                    span,
+                };
+                binary(zero, Operator::Subtract, exp)
+            })
+            .infix_left(oper("*/"), 2, binary)
+            .infix_left(oper("+-"), 1, binary)
+            .infix_left(oper("=<>"), 0, binary)
+    })
+}
+
+fn term<'a>(exp: Exp<'a>) -> impl Parser<Input = StrStream<'a>, Output = Expression> {
+    choice![
+        let_exp(exp.clone()),
+        if_exp(exp.clone()),
+        atom_exp(),
+        seq!(symbol('('), exp: exp, symbol(')') => exp),
+    ]
+    .label(|| "syntax error")
+}
+
+// Atom
+
+fn atom_exp<'a>() -> impl Parser<Input = StrStream<'a>, Output = Expression> {
+    choice![
+        number().map(Atom::Int),
+        keyword("true").map(|_| Atom::Bool(true)),
+        keyword("false").map(|_| Atom::Bool(false)),
+        variable().map(Atom::Var),
+    ]
+    .spanned()
+    .map(|(atom, span)| Expression {
+        kind: ExpressionKind::Atom(atom),
+        span,
+    })
+    .use_left(spaces())
+}
+
+// Operator
+
+fn oper<'a>(opers: &'static str) -> impl Parser<Input = StrStream<'a>, Output = Operator> {
+    one_of(opers)
+        .map(|c| match c {
+            '+' => Operator::Add,
+            '-' => Operator::Subtract,
+            '*' => Operator::Multiply,
+            '/' => Operator::Divide,
+            '=' => Operator::Eq,
+            '<' => Operator::Lt,
+            _ => Operator::Gt,
+        })
+        .use_left(spaces())
+}
+
+fn binary(lhs: Expression, oper: Operator, rhs: Expression) -> Expression {
+    Expression {
+        span: lhs.span.start..rhs.span.end,
+        kind: ExpressionKind::Oper {
+            oper,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        },
+    }
+}
+
+// If
+
+fn if_exp<'a>(exp: Exp<'a>) -> impl Parser<Input = StrStream<'a>, Output = Expression> {
+    seq!(
+        span: keyword_token("if"),
+        pred: exp,
+        keyword_token("then"),
+        then: exp,
+        keyword_token("else"),
+        or_else: exp,
+        => Expression {
+            span: span.start..or_else.span.end,
+            kind: ExpressionKind::If {
+                pred: Box::new(pred),
+                then: Box::new(then),
+                or_else: Box::new(or_else),
+            },
+        }
+    )
+}
+
+// Let
+
+fn let_exp<'a>(exp: Exp<'a>) -> impl Parser<Input = StrStream<'a>, Output = Expression> {
+    seq!(
+        span: keyword_token("let"),
+        bindings: binding(exp.clone()).split(symbol(',')),
+        keyword_token("in"),
+        exp: exp,
+        => Expression {
+            span: span.start..exp.span.end,
+            kind: ExpressionKind::Let {
+                bindings,
+                exp: Box::new(exp),
+            },
+        }
+    )
+}
+
+fn binding<'a>(exp: Exp<'a>) -> impl Parser<Input = StrStream<'a>, Output = Binding> {
+    seq!(
+        name: variable().spanned(),
+        spaces(),
+        symbol('='),
+        exp: exp,
+        => {
+            let (name, name_span) = name;
+            Binding {
+                name,
+                name_span,
+                exp: Box::new(exp),
+            }
+        }
+    )
+}
+
+// Helpers
+
+fn word<'a>() -> impl Parser<Input = StrStream<'a>, Output = &'a str> {
+    alphabetic()
+        .or(char('_'))
+        .pair(take_while(|c: &char| {
+            c.is_ascii_alphanumeric() || *c == '_'
+        }))
+        .recognize()
+}
+
+// Whole words only, `letter` is not `let`.
+fn keyword<'a>(keyword: &'static str) -> impl Parser<Input = StrStream<'a>, Output = ()> {
+    tag(keyword)
+        .not_followed_by(satisfy(
+            |c| c.is_ascii_alphanumeric() || c == '_',
+            "expect identifier char",
+        ))
+        .map(|_| ())
+        .label(move || format!("expect \"{}\"", keyword))
+}
+
+fn keyword_token<'a>(keyword: &'static str) -> impl Parser<Input = StrStream<'a>, Output = Span> {
+    self::keyword(keyword)
+        .spanned()
+        .map(|(_, span)| span)
+        .use_left(spaces())
+}
+
+fn variable<'a>() -> impl Parser<Input = StrStream<'a>, Output = String> {
+    word()
+        .filter(|word| !KEYWORDS.contains(word))
+        .map(str::to_owned)
+        .label(|| "expect identifier")
+}
+
+fn symbol<'a>(symbol: char) -> impl Parser<Input = StrStream<'a>, Output = char> {
+    char(symbol).use_left(spaces())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{run_infix, Error};
+
+    fn sexp(input: &str) -> String {
+        parse(input).unwrap().to_string()
+    }
+
+    fn error(input: &str) -> usize {
+        parse(input).unwrap_err().pos
+    }
+
+    fn eval(input: &str) -> String {
+        match run_infix(input) {
+            Ok(val) => val.to_string(),
+            Err(err) => err.kind().to_owned(),
+        }
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        assert_eq!(sexp("1 + 2 * 3"), "(+ 1 (* 2 3))");
+        assert_eq!(sexp("1 * 2 + 3"), "(+ (* 1 2) 3)");
+        assert_eq!(sexp("1 - 2 - 3"), "(- (- 1 2) 3)");
+        assert_eq!(sexp("8 / 4 / 2"), "(/ (/ 8 4) 2)");
+        assert_eq!(sexp("1 + 2 < 3 * 4"), "(< (+ 1 2) (* 3 4))");
+    }
+
+    #[test]
+    fn prefix_and_parentheses() {
+        assert_eq!(sexp("-x * 2"), "(* (- 0 x) 2)");
+        assert_eq!(sexp("- - 1"), "(- 0 (- 0 1))");
+        assert_eq!(sexp("(1 + 2) * 3"), "(* (+ 1 2) 3)");
+        assert_eq!(sexp("-(1 - 2)"), "(- 0 (- 1 2))");
+    }
+
+    #[test]
+    fn let_and_if() {
+        assert_eq!(
+            sexp("let x = 2, y = x + 3 in x * y"),
+            "(let (x 2) (y (+ x 3)) (* x y))"
+        );
+        assert_eq!(sexp("if x < 0 then -x else x"), "(if (< x 0) (- 0 x) x)");
+        assert_eq!(sexp("letter + iffy"), "(+ letter iffy)");
+        let exp = parse("let x = 1 in x + 10").unwrap();
+        assert_eq!(exp.span, 0..19);
+        let ExpressionKind::Let { bindings, .. } = &exp.kind else {
+            panic!("expect let");
+        };
+        assert_eq!(bindings[0].name_span, 4..5);
+    }
+
+    #[test]
+    fn error_positions() {
+        assert_eq!(error("1 +"), 3);
+        assert_eq!(error("* 2"), 0);
+        assert_eq!(error("(1 + 2"), 6);
+        assert_eq!(error("if true then 1"), 14);
+    }
+
+    #[test]
+    fn run_end_to_end() {
+        assert_eq!(eval("let x = 2, y = 2 + 3 in x * y"), "10");
+        assert_eq!(eval("if 1 < 0 then 1 else -1"), "-1");
+        assert_eq!(eval("let x = 7 in if x > 5 then x * x else 0"), "49");
+        assert_eq!(eval("10 / (5 - 5)"), "DivisionByZero");
+        assert_eq!(eval("1 + true"), "TypeMismatch");
+        assert_eq!(eval("x"), "UnboundIdentifier");
+        assert!(matches!(run_infix("1 +"), Err(Error::Parser(_))));
+    }
+}
@@ -0,0 +1,271 @@
+//! Tokens of S-expression sources, for the reader and for editors.
+//!
+//! Spans are character offsets into the source. Quotes and strings are
+//! tokenized though the language has no use for them yet.
+//!
+//! ```
+//! use s_expression::lexer::{tokenize, TokenKind};
+//!
+//! let kinds: Vec<_> = tokenize("(+ x 1) #").into_iter().map(|token| token.kind).collect();
+//! assert_eq!(
+//!     kinds,
+//!     [
+//!         TokenKind::LParen,
+//!         TokenKind::Ident("+".into()),
+//!         TokenKind::Ident("x".into()),
+//!         TokenKind::Int(1),
+//!         TokenKind::RParen,
+//!         TokenKind::Error,
+//!     ]
+//! );
+//! ```
+
+use parsec::{consumers::*, Context, Parser, Sliceable, StrStream};
+
+use super::Span;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    LParen,
+    RParen,
+    Int(i64),
+    Bool(bool),
+    /// An identifier or an operator
+    Ident(String),
+    Quote,
+    Str(String),
+    /// Input that is no token
+    Error,
+}
+
+/// Tokens of the whole source, with an `Error` token wherever it has none.
+pub fn tokenize(source: &str) -> Vec<Token> {
+    lex(source).0
+}
+
+// Like `tokenize`, with the errors of the `Error` tokens.
+pub(super) fn lex(source: &str) -> (Vec<Token>, Vec<parsec::Error>) {
+    let mut context = Context::from_str(source);
+    // Never fails: a bad token is read as an `Error` token.
+    let tokens = context.parse_by(lexer()).unwrap_or_default();
+    (tokens, context.take_errors())
+}
+
+/// Tokens of the whole source, with the trace of the named parsers of the
+/// lexer.
+pub(super) fn trace(source: &str) -> (Vec<Token>, parsec::trace::Trace) {
+    let mut context = Context::from_str(source);
+    context.enable_tracing();
+    let tokens = context.parse_by(lexer()).unwrap_or_default();
+    (tokens, context.take_trace().unwrap_or_default())
+}
+
+// Borrows the source rather than copying it. Built for every source, which
+// costs nothing: no parser is boxed, and none holds shared state, so `lex`
+// runs on many threads at the same time.
+fn lexer<'a>() -> impl Parser<Input = StrStream<'a>, Output = Vec<Token>> + Send + Sync {
+    spaces().use_right(token().use_left(spaces()).many_till(eos()))
+}
+
+// Any stream of chars whose slices read as `str`, such as `StrStream`,
+// `SharedStrStream` or `PartialStream`.
+pub(super) trait Input: Sliceable<Item = char, Slice: AsRef<str>> {}
+
+impl<S> Input for S where S: Sliceable<Item = char, Slice: AsRef<str>> {}
+
+// Whitespaces, borrowed rather than collected.
+pub(super) fn spaces<S: Input>() -> impl Parser<Input = S, Output = S::Slice> {
+    take_while(|c: &char| c.is_whitespace())
+}
+
+// Tokens are named for `analysis::trace`.
+pub(super) fn token<S: Input>() -> impl Parser<Input = S, Output = Token> {
+    // A bad token is skipped up to the next delimiter.
+    let bad = take_while1(|c: &char| !c.is_whitespace() && *c != '(' && *c != ')')
+        .map(|_| TokenKind::Error)
+        .named("bad token");
+    char('(')
+        .map(|_| TokenKind::LParen)
+        .named("open")
+        .or(char(')').map(|_| TokenKind::RParen).named("close"))
+        .or(char('\'').map(|_| TokenKind::Quote).named("quote"))
+        .or(string_literal().map(TokenKind::Str).named("string"))
+        .or(atom())
+        .recover_with(bad)
+        .spanned()
+        .map(|(kind, span)| Token { kind, span })
+        .named("token")
+}
+
+fn atom<S: Input>() -> impl Parser<Input = S, Output = TokenKind> {
+    // Whole words only: `trueish` is an identifier rather than `true` and
+    // `ish`, `1x` is no number and `-foo` no operator.
+    let bool = tag("true").map(|_| true).or(tag("false").map(|_| false));
+    let word = number()
+        .map(TokenKind::Int)
+        .named("number")
+        .or(bool.map(TokenKind::Bool).named("bool"))
+        .not_followed_by(identifier_char())
+        .or(identifier::<S>()
+            .map(|ident| TokenKind::Ident(ident.as_ref().to_owned()))
+            .named("identifier"));
+    let oper = one_of("+-*/=<>")
+        .recognize()
+        .map(|oper: S::Slice| TokenKind::Ident(oper.as_ref().to_owned()))
+        .named("operator")
+        .not_followed_by(identifier_char());
+    word.or(oper).label(|| "syntax error").named("atom")
+}
+
+fn identifier<S: Input>() -> impl Parser<Input = S, Output = S::Slice> {
+    alphabetic()
+        .or(char('_'))
+        .pair(take_while(|c: &char| {
+            c.is_ascii_alphanumeric() || *c == '_'
+        }))
+        .recognize()
+}
+
+fn identifier_char<S: Input>() -> impl Parser<Input = S, Output = char> {
+    satisfy(
+        |c| c.is_ascii_alphanumeric() || c == '_',
+        "expect identifier char",
+    )
+}
+
+// A string in double quotes, where `\` escapes the next char.
+fn string_literal<S: Input>() -> impl Parser<Input = S, Output = String> {
+    let escaped = char('\\').use_right(any_char()).map(|c| match c {
+        'n' => '\n',
+        't' => '\t',
+        c => c,
+    });
+    none_of("\"\\")
+        .or(escaped)
+        .many()
+        .between(char('"'), char('"'))
+        .map(|chars| chars.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(source: &str) -> Vec<(TokenKind, Span)> {
+        tokenize(source)
+            .into_iter()
+            .map(|token| (token.kind, token.span))
+            .collect()
+    }
+
+    fn ident(name: &str) -> TokenKind {
+        TokenKind::Ident(name.into())
+    }
+
+    #[test]
+    fn tokens_and_spans() {
+        assert_eq!(
+            tokens(" (let (x_1 10)\n  (< x_1 -))"),
+            [
+                (TokenKind::LParen, 1..2),
+                (ident("let"), 2..5),
+                (TokenKind::LParen, 6..7),
+                (ident("x_1"), 7..10),
+                (TokenKind::Int(10), 11..13),
+                (TokenKind::RParen, 13..14),
+                (TokenKind::LParen, 17..18),
+                (ident("<"), 18..19),
+                (ident("x_1"), 20..23),
+                (ident("-"), 24..25),
+                (TokenKind::RParen, 25..26),
+                (TokenKind::RParen, 26..27),
+            ]
+        );
+        assert_eq!(
+            tokens("true trueish false)"),
+            [
+                (TokenKind::Bool(true), 0..4),
+                (ident("trueish"), 5..12),
+                (TokenKind::Bool(false), 13..18),
+                (TokenKind::RParen, 18..19),
+            ]
+        );
+        assert_eq!(
+            tokens(r#"'x "a\"b\n""#),
+            [
+                (TokenKind::Quote, 0..1),
+                (ident("x"), 1..2),
+                (TokenKind::Str("a\"b\n".into()), 3..11),
+            ]
+        );
+        assert_eq!(tokens(""), []);
+    }
+
+    // Spans are char offsets, not byte offsets.
+    #[test]
+    fn char_offsets() {
+        assert_eq!(
+            tokens("(é 1)"),
+            [
+                (TokenKind::LParen, 0..1),
+                (TokenKind::Error, 1..2),
+                (TokenKind::Int(1), 3..4),
+                (TokenKind::RParen, 4..5),
+            ]
+        );
+    }
+
+    #[test]
+    fn whole_words() {
+        for source in ["+x", "-foo", "<1", "1x", "=_"] {
+            let (tokens, errors) = lex(source);
+            assert_eq!(
+                tokens,
+                [Token {
+                    kind: TokenKind::Error,
+                    span: 0..source.chars().count(),
+                }],
+                "{}",
+                source
+            );
+            assert_eq!(
+                errors,
+                [parsec::Error::new(1, "syntax error")],
+                "{}",
+                source
+            );
+        }
+        // Delimiters end operators.
+        assert_eq!(
+            tokens("(+(-))"),
+            [
+                (TokenKind::LParen, 0..1),
+                (ident("+"), 1..2),
+                (TokenKind::LParen, 2..3),
+                (ident("-"), 3..4),
+                (TokenKind::RParen, 4..5),
+                (TokenKind::RParen, 5..6),
+            ]
+        );
+    }
+
+    #[test]
+    fn bad_tokens() {
+        let (tokens, errors) = lex("(+ # 1)");
+        assert_eq!(
+            tokens[2],
+            Token {
+                kind: TokenKind::Error,
+                span: 3..4,
+            }
+        );
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(errors, [parsec::Error::new(3, "syntax error")]);
+    }
+}
@@ -34,15 +34,118 @@
 
 #![feature(let_else)]
 
+pub mod analysis;
+pub mod cst;
 mod evaluator;
 mod expression;
+#[cfg(feature = "tokio")]
+mod form_stream;
+#[cfg(feature = "infix")]
+mod infix;
+pub mod lexer;
+mod limits;
 mod parser;
+mod printer;
+mod reader;
 mod result;
+mod session;
+mod streaming;
 
-pub use result::{Error, Result, Val};
+#[cfg(feature = "tokio")]
+pub use form_stream::FormStream;
+pub use limits::{CancellationToken, Limits};
+pub use result::{Error, Result, Span, Val};
+pub use session::{Completion, Session};
+pub use streaming::{FormReader, FormStatus, ParsedForm, SourceForm};
 
+/// Evaluates an S-expression.
+///
+/// The parsers hold no shared state, so `run` can be called from many
+/// threads at the same time:
+///
+/// ```
+/// let results: Vec<_> = std::thread::scope(|scope| {
+///     let handles: Vec<_> = (1..=8)
+///         .map(|n| scope.spawn(move || s_expression::run(format!("(let (x {}) (* x x))", n))))
+///         .collect();
+///     handles.into_iter().map(|h| h.join().unwrap().unwrap().to_string()).collect()
+/// });
+/// assert_eq!(results, ["1", "4", "9", "16", "25", "36", "49", "64"]);
+/// ```
 pub fn run(input: impl AsRef<str>) -> Result<Val> {
+    run_with_limits(input, &Limits::default())
+}
+
+pub fn run_with_limits(input: impl AsRef<str>, limits: &Limits) -> Result<Val> {
     parser::parse(input.as_ref())
         .map_err(Error::Parser)
-        .and_then(evaluator::eval)
+        .and_then(|exp| evaluator::eval(&exp, limits))
+}
+
+/// Evaluates an expression in infix notation, such as
+/// `let x = 2, y = 2 + 3 in x * y`.
+#[cfg(feature = "infix")]
+pub fn run_infix(input: impl AsRef<str>) -> Result<Val> {
+    run_infix_with_limits(input, &Limits::default())
+}
+
+#[cfg(feature = "infix")]
+pub fn run_infix_with_limits(input: impl AsRef<str>, limits: &Limits) -> Result<Val> {
+    infix::parse(input.as_ref())
+        .map_err(Error::Parser)
+        .and_then(|exp| evaluator::eval(&exp, limits))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn send_and_sync() {
+        assert_send_sync::<FormReader>();
+        assert_send_sync::<SourceForm>();
+        assert_send_sync::<ParsedForm>();
+        assert_send_sync::<lexer::Token>();
+        assert_send_sync::<Limits>();
+    }
+
+    #[test]
+    fn parse_from_many_threads() {
+        let source = |n: usize| format!("(define x {})\n(let (y (+ x 1)) (* x y)) #", n);
+        thread::scope(|scope| {
+            let handles: Vec<_> = (1..=8)
+                .map(|n| {
+                    scope.spawn(move || {
+                        let source = source(n);
+                        let tokens = lexer::tokenize(&source);
+                        let (forms, errors) = parser::parse_forms_partial(&source);
+                        let val = run(format!("(let (x {}) (* x (+ x 1)))", n)).unwrap();
+                        let mut reader = FormReader::new();
+                        reader.feed(&source);
+                        (
+                            tokens.len(),
+                            forms.len(),
+                            errors.len(),
+                            val.to_string(),
+                            reader,
+                        )
+                    })
+                })
+                .collect();
+            for (n, handle) in (1..=8).zip(handles) {
+                let (tokens, forms, errors, val, mut reader) = handle.join().unwrap();
+                assert_eq!((tokens, forms, errors), (22, 3, 1));
+                assert_eq!(val, (n * (n + 1)).to_string());
+                // Read on another thread, finished on this one.
+                let FormStatus::Form(form) = reader.next_form() else {
+                    panic!("expect a form");
+                };
+                assert_eq!(form.text, format!("(define x {})", n));
+            }
+        });
+    }
 }
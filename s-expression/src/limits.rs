@@ -0,0 +1,88 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// Evaluation budget for untrusted input.
+///
+/// Every limit is off by default. A limit that is hit stops the evaluation
+/// with the matching [`Error`] carrying the span of the expression being
+/// evaluated at that point.
+///
+/// [`Error`]: crate::Error
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    pub(super) max_steps: Option<u64>,
+    pub(super) max_depth: Option<usize>,
+    pub(super) max_values: Option<u64>,
+    pub(super) timeout: Option<Duration>,
+    pub(super) cancellation: Option<CancellationToken>,
+}
+
+impl Limits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum number of expressions evaluated.
+    pub fn max_steps(mut self, steps: u64) -> Self {
+        self.max_steps = Some(steps);
+        self
+    }
+
+    /// Maximum depth of the continuation: operands and conditions waiting
+    /// to be evaluated. `if` branches, `let` bodies and variables do not add
+    /// to it.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Maximum number of bindings made by `let`.
+    pub fn max_values(mut self, values: u64) -> Self {
+        self.max_values = Some(values);
+        self
+    }
+
+    /// Wall-clock deadline, relative to the start of each evaluation.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Token checked by the evaluator before every step.
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+}
+
+/// Shared flag to interrupt a running evaluation from another thread.
+///
+/// Once cancelled, evaluations using the token fail with
+/// `Error::Interrupted` until it is [`reset`](CancellationToken::reset).
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
@@ -1,119 +1,424 @@
-use parsec::{consumers::*, fail, Any, Context, Parser};
+use std::borrow::Cow;
 
-use super::expression::{Atom, Env, Expression, Operator};
+use parsec::{consumers::*, trace::Trace, Context, Parser};
+
+use super::{
+    expression::{Atom, Binding, Expression, ExpressionKind, Form, Operator},
+    lexer::{self, Token, TokenKind},
+    reader::{datum, Sexp, SexpKind},
+    Span,
+};
+
+// Syntax errors are collected rather than returned, each bad datum becomes
+// an `Error` node in place.
+type Errors = Vec<parsec::Error>;
 
 pub(super) fn parse(input: &str) -> parsec::Result<Expression> {
-    Context::from_str(input).parse_by(exp())
-}
-
-fn exp() -> impl Parser<Output = Expression> {
-    let_exp()
-        .or(if_exp())
-        .or(oper_exp())
-        .or(atom_exp())
-        .or(fail(|| "syntax error"))
-        .trim_whitespaces()
-}
-
-// Atom
-
-fn atom_exp() -> impl Parser<Output = Expression> {
-    number()
-        .map(Atom::Int)
-        .or(bool().map(Atom::Bool))
-        .or(identifier().map(Atom::Var))
-        .map(Expression::Atom)
-        .trim_whitespaces()
-        .or(fail(|| "atom expression syntax error"))
-}
-
-// Operator
-
-fn oper_exp() -> impl Parser<Output = Expression> {
-    oper()
-        .flat_map(move |oper| {
-            exp()
-                .map_box()
-                .pair(exp().map_box())
-                .map(move |(lhs, rhs)| Expression::Oper { oper, lhs, rhs })
-        })
-        .in_parentheses()
-        .or(fail(|| "operator expression syntax error"))
-}
-
-fn oper() -> impl Parser<Output = Operator> {
-    any_char().and_then(|c| match c {
-        '+' => Ok(Operator::Add),
-        '-' => Ok(Operator::Subtract),
-        '*' => Ok(Operator::Multiply),
-        '/' => Ok(Operator::Divide),
-        '=' => Ok(Operator::Eq),
-        '<' => Ok(Operator::Lt),
-        '>' => Ok(Operator::Gt),
-        _ => Err("expect operator"),
+    let (tokens, lexer_errors) = lexer::lex(input);
+    let mut context = Context::from_slice(&tokens);
+    let sexp = context.parse_by(datum(input.chars().count()))?;
+    // Tokens after the datum are no concern of it.
+    let mut errors: Errors = lexer_errors
+        .into_iter()
+        .filter(|err| err.pos < sexp.span.end)
+        .chain(context.take_errors())
+        .collect();
+    errors.sort_by_key(|err| err.pos);
+    let mut form_errors = Errors::new();
+    let exp = exp(&sexp, &mut form_errors);
+    errors.extend(not_reported(form_errors, &tokens));
+    first_error(errors).map_or(Ok(exp), Err)
+}
+
+/// Trace of the lexer, then of the reader, over the whole source.
+pub(super) fn trace(input: &str) -> Trace {
+    let (tokens, mut trace) = lexer::trace(input);
+    let mut context = Context::from_slice(&tokens);
+    context.enable_tracing();
+    let _ = context.parse_by(datum(input.chars().count()).many_till(eos()));
+    trace.append(context.take_trace().unwrap_or_default());
+    trace
+}
+
+// Unlike `parse`, requires the whole input to be consumed.
+pub(super) fn parse_forms(input: &str) -> parsec::Result<Vec<Form>> {
+    let (forms, errors) = parse_forms_partial(input);
+    first_error(errors).map_or(Ok(forms), Err)
+}
+
+/// All the forms, with `Error` nodes where they are malformed, and all the
+/// syntax errors: those of the lexer and reader first, then those of
+/// malformed forms, which may follow from the former.
+pub(super) fn parse_forms_partial(input: &str) -> (Vec<Form>, Errors) {
+    let (tokens, mut errors) = lexer::lex(input);
+    let mut context = Context::from_slice(&tokens);
+    // Never fails: `datum` only does where the tokens end.
+    let sexps = context
+        .parse_by(datum(input.chars().count()).many_till(eos()))
+        .unwrap_or_default();
+    errors.extend(context.take_errors());
+    errors.sort_by_key(|err| err.pos);
+    let mut form_errors = Errors::new();
+    let forms = sexps
+        .iter()
+        .map(|sexp| form(sexp, &mut form_errors))
+        .collect();
+    errors.extend(not_reported(form_errors, &tokens));
+    (forms, errors)
+}
+
+fn first_error(errors: Errors) -> Option<parsec::Error> {
+    errors.into_iter().next()
+}
+
+// Errors of malformed forms, but those within a token the lexer or reader
+// reported already: `(# 1)` is a bad token, not a bad form too.
+fn not_reported(errors: Errors, tokens: &[Token]) -> impl Iterator<Item = parsec::Error> + '_ {
+    let reported = tokens.iter().filter(|token| {
+        matches!(
+            token.kind,
+            TokenKind::Error | TokenKind::Quote | TokenKind::Str(_)
+        )
+    });
+    errors
+        .into_iter()
+        .filter(move |err| !reported.clone().any(|token| token.span.contains(&err.pos)))
+}
+
+// Reserved words, never bound to a variable.
+const KEYWORDS: [&str; 3] = ["define", "if", "let"];
+
+fn form(sexp: &Sexp, errors: &mut Errors) -> Form {
+    match list_head(sexp) {
+        Some(("define", items)) => match define_form(sexp, items, errors) {
+            Ok(binding) => Form::Define(binding),
+            Err(err) => {
+                errors.push(err);
+                Form::Exp(error_exp(sexp))
+            }
+        },
+        _ => Form::Exp(exp(sexp, errors)),
+    }
+}
+
+// Define
+
+fn define_form(sexp: &Sexp, items: &[Sexp], errors: &mut Errors) -> parsec::Result<Binding> {
+    let [_, name, exp_sexp] = items else {
+        return Err(arity_error(sexp, items, 3, "define syntax error"));
+    };
+    let (name, name_span) = identifier(name)?;
+    Ok(Binding {
+        name,
+        name_span,
+        exp: Box::new(exp(exp_sexp, errors)),
     })
 }
 
-// If
+// Expression
+//
+// Syntax checks run on an explicit stack too: a list pushes a task to build
+// its expression, then tasks for its sub-expressions, whose results land on
+// `output` in order.
 
-fn if_exp() -> impl Parser<Output = Expression> {
-    string("if")
-        .flat_map(|_| exp().map_box().pair(exp().map_box()).pair(exp().map_box()))
-        .map(|((pred, then), or_else)| Expression::If {
-            pred,
-            then,
-            or_else,
-        })
-        .in_parentheses()
-        .or(fail(|| "if expression syntax error"))
+enum Task<'a> {
+    Check(&'a Sexp),
+    Oper(Operator, Span),
+    If(Span),
+    Let(Vec<(String, Span)>, Span),
 }
 
-// Let
+fn exp(sexp: &Sexp, errors: &mut Errors) -> Expression {
+    let mut tasks = vec![Task::Check(sexp)];
+    let mut output: Vec<Expression> = Vec::new();
+    while let Some(task) = tasks.pop() {
+        let exp = match task {
+            Task::Check(sexp) => match check(sexp, &mut tasks, errors) {
+                Ok(Some(kind)) => Expression {
+                    kind,
+                    span: sexp.span.clone(),
+                },
+                Ok(None) => continue,
+                Err(err) => {
+                    errors.push(err);
+                    error_exp(sexp)
+                }
+            },
+            Task::Oper(oper, span) => {
+                let rhs = Box::new(output.pop().unwrap());
+                let lhs = Box::new(output.pop().unwrap());
+                Expression {
+                    kind: ExpressionKind::Oper { oper, lhs, rhs },
+                    span,
+                }
+            }
+            Task::If(span) => {
+                let or_else = Box::new(output.pop().unwrap());
+                let then = Box::new(output.pop().unwrap());
+                let pred = Box::new(output.pop().unwrap());
+                Expression {
+                    kind: ExpressionKind::If {
+                        pred,
+                        then,
+                        or_else,
+                    },
+                    span,
+                }
+            }
+            Task::Let(names, span) => {
+                let exp = Box::new(output.pop().unwrap());
+                let exps = output.split_off(output.len() - names.len());
+                let bindings = names
+                    .into_iter()
+                    .zip(exps)
+                    .map(|((name, name_span), exp)| Binding {
+                        name,
+                        name_span,
+                        exp: Box::new(exp),
+                    })
+                    .collect();
+                Expression {
+                    kind: ExpressionKind::Let { bindings, exp },
+                    span,
+                }
+            }
+        };
+        output.push(exp);
+    }
+    output.pop().unwrap()
+}
 
-fn let_exp() -> impl Parser<Output = Expression> {
-    string("let")
-        .flat_map(|_| {
-            binding()
-                .many()
-                .map(|bindings| bindings.into_iter().collect::<Env>())
-                .pair(exp().map_box())
-        })
-        .map(|(env, exp)| Expression::Let { env, exp })
-        .in_parentheses()
-        .or(fail(|| "let expression syntax error"))
+// Returns an atom, or schedules the tasks building a list. Malformed `let`
+// bindings are reported to `errors` and left out, the rest of the `let` is
+// still checked.
+fn check<'a>(
+    sexp: &'a Sexp,
+    tasks: &mut Vec<Task<'a>>,
+    errors: &mut Errors,
+) -> parsec::Result<Option<ExpressionKind>> {
+    let items = match &sexp.kind {
+        SexpKind::Int(val) => return Ok(Some(ExpressionKind::Atom(Atom::Int(*val)))),
+        SexpKind::Bool(val) => return Ok(Some(ExpressionKind::Atom(Atom::Bool(*val)))),
+        SexpKind::Symbol(_) => {
+            let (id, _) = identifier(sexp)?;
+            return Ok(Some(ExpressionKind::Atom(Atom::Var(id))));
+        }
+        // Reported by the reader already
+        SexpKind::Error => return Ok(Some(ExpressionKind::Error)),
+        SexpKind::List(items) => items,
+    };
+    let span = sexp.span.clone();
+    let args = match list_head(sexp) {
+        Some(("if", _)) => {
+            let [_, pred, then, or_else] = &items[..] else {
+                return Err(arity_error(sexp, items, 4, "if expression syntax error"));
+            };
+            tasks.push(Task::If(span));
+            vec![pred, then, or_else]
+        }
+        Some(("let", _)) => {
+            let [_, bindings @ .., exp] = &items[..] else {
+                return Err(arity_error(sexp, items, 2, "let expression syntax error"));
+            };
+            let mut names = Vec::new();
+            let mut args = Vec::new();
+            for binding in bindings {
+                match let_binding(binding) {
+                    Ok((name, exp)) => {
+                        names.push(name);
+                        args.push(exp);
+                    }
+                    Err(err) => errors.push(err),
+                }
+            }
+            args.push(exp);
+            tasks.push(Task::Let(names, span));
+            args
+        }
+        Some((head, _)) if oper(head).is_some() => {
+            let [_, lhs, rhs] = &items[..] else {
+                return Err(arity_error(
+                    sexp,
+                    items,
+                    3,
+                    "operator expression syntax error",
+                ));
+            };
+            tasks.push(Task::Oper(oper(head).unwrap(), span));
+            vec![lhs, rhs]
+        }
+        // At the head, or where it is missing.
+        _ => {
+            let pos = items
+                .first()
+                .map_or(sexp.span.start + 1, |head| head.span.start);
+            return Err(parsec::Error::new(pos, "syntax error"));
+        }
+    };
+    tasks.extend(args.into_iter().rev().map(Task::Check));
+    Ok(None)
 }
 
-fn binding() -> impl Parser<Output = (String, Box<Expression>)> {
-    identifier()
-        .pair(exp().map_box())
-        .in_parentheses()
-        .or(fail(|| "expect binding"))
+fn let_binding(binding: &Sexp) -> parsec::Result<((String, Span), &Sexp)> {
+    let SexpKind::List(items) = &binding.kind else {
+        return Err(syntax_error(binding, "expect binding"));
+    };
+    let [name, exp] = &items[..] else {
+        return Err(syntax_error(binding, "expect binding"));
+    };
+    Ok((identifier(name)?, exp))
 }
 
-// Ext
+// Helpers
 
-trait ParserExt: Parser {
-    fn map_box(self) -> Any<Box<Self::Output>>;
-    fn trim_whitespaces(self) -> Any<Self::Output>;
-    fn in_parentheses(self) -> Any<Self::Output>;
+fn list_head(sexp: &Sexp) -> Option<(&str, &[Sexp])> {
+    let SexpKind::List(items) = &sexp.kind else {
+        return None;
+    };
+    match items.first().map(|head| &head.kind) {
+        Some(SexpKind::Symbol(head)) => Some((head, items)),
+        _ => None,
+    }
+}
+
+fn identifier(sexp: &Sexp) -> parsec::Result<(String, Span)> {
+    match &sexp.kind {
+        SexpKind::Symbol(id) if oper(id).is_none() && !KEYWORDS.contains(&id.as_str()) => {
+            Ok((id.clone(), sexp.span.clone()))
+        }
+        _ => Err(syntax_error(sexp, "expect identifier")),
+    }
+}
+
+fn oper(symbol: &str) -> Option<Operator> {
+    let oper = match symbol {
+        "+" => Operator::Add,
+        "-" => Operator::Subtract,
+        "*" => Operator::Multiply,
+        "/" => Operator::Divide,
+        "=" => Operator::Eq,
+        "<" => Operator::Lt,
+        ">" => Operator::Gt,
+        _ => return None,
+    };
+    Some(oper)
+}
+
+fn error_exp(sexp: &Sexp) -> Expression {
+    Expression {
+        kind: ExpressionKind::Error,
+        span: sexp.span.clone(),
+    }
+}
+
+fn syntax_error(sexp: &Sexp, msg: impl Into<Cow<'static, str>>) -> parsec::Error {
+    parsec::Error::new(sexp.span.start, msg)
+}
+
+// A list of `items` that should have `len` of them, reported at the first
+// extra item or after the last one.
+fn arity_error(
+    sexp: &Sexp,
+    items: &[Sexp],
+    len: usize,
+    msg: impl Into<Cow<'static, str>>,
+) -> parsec::Error {
+    let pos = match (items.get(len), items.last()) {
+        (Some(extra), _) => extra.span.start,
+        (None, Some(last)) => last.span.end,
+        (None, None) => sexp.span.start + 1,
+    };
+    parsec::Error::new(pos, msg)
 }
 
-impl<T> ParserExt for T
-where
-    T: 'static + Parser,
-{
-    fn map_box(self) -> Any<Box<Self::Output>> {
-        self.map(Box::new).to_any()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(input: &str) -> (usize, String) {
+        let err = parse(input).unwrap_err();
+        (err.pos, err.msg.into_owned())
+    }
+
+    #[test]
+    fn error_positions() {
+        for (input, pos, msg) in [
+            ("(iffy 1)", 1, "syntax error"),
+            ("(1 2)", 1, "syntax error"),
+            ("()", 1, "syntax error"),
+            ("(if 1)", 5, "if expression syntax error"),
+            ("(if 1 2 3 4)", 10, "if expression syntax error"),
+            ("(+ 1)", 4, "operator expression syntax error"),
+            ("(+ 1 2 3)", 7, "operator expression syntax error"),
+            ("(let)", 4, "let expression syntax error"),
+            ("(let x 1)", 5, "expect binding"),
+            ("(let (1 2) x)", 6, "expect identifier"),
+            ("(if (+ 1) 2 3)", 8, "operator expression syntax error"),
+            ("(+ 1 (if 1))", 10, "if expression syntax error"),
+            ("(+ 1 2", 6, "expect ')'"),
+            (")", 0, "unexpected ')'"),
+        ] {
+            assert_eq!(error(input), (pos, msg.to_owned()), "{}", input);
+        }
+        let (_, errors) = parse_forms_partial("(define x)\n(define x 1 2)");
+        let errors: Vec<_> = errors.iter().map(|err| err.pos).collect();
+        assert_eq!(errors, [9, 23]);
+    }
+
+    fn partial(input: &str) -> (Vec<String>, Vec<(usize, String)>) {
+        let (forms, errors) = parse_forms_partial(input);
+        let forms = forms
+            .iter()
+            .map(|form| match form {
+                Form::Exp(exp) => exp.to_string(),
+                Form::Define(binding) => format!("(define {} {})", binding.name, binding.exp),
+            })
+            .collect();
+        let errors = errors
+            .into_iter()
+            .map(|err| (err.pos, err.msg.into_owned()))
+            .collect();
+        (forms, errors)
+    }
+
+    #[test]
+    fn let_recovers_per_binding() {
+        let (forms, errors) = partial("(let (x 1) (2 3) y (z) (w (+ 1)) (+ x w))");
+        assert_eq!(forms, ["(let (x 1) (w <error>) (+ x w))"]);
+        assert_eq!(
+            errors,
+            [
+                (12, "expect identifier".to_owned()),
+                (17, "expect binding".to_owned()),
+                (19, "expect binding".to_owned()),
+                (30, "operator expression syntax error".to_owned()),
+            ]
+        );
     }
 
-    fn trim_whitespaces(self) -> Any<Self::Output> {
-        self.between(whitespaces(), whitespaces()).to_any()
+    #[test]
+    fn errors_of_bad_tokens_are_not_repeated() {
+        for input in ["(# 1 2)", "('x 1)", "(\"x\" 1)"] {
+            let (_, errors) = partial(input);
+            assert_eq!(errors.len(), 1, "{}: {:?}", input, errors);
+            assert_eq!(errors[0].0, 1, "{}", input);
+        }
+        // Other errors of the form still are.
+        let (_, errors) = partial("(+ # 1 2)");
+        let errors: Vec<_> = errors.iter().map(|(pos, _)| *pos).collect();
+        assert_eq!(errors, [3, 7]);
     }
 
-    fn in_parentheses(self) -> Any<Self::Output> {
-        self.trim_whitespaces()
-            .between(char('('), char(')'))
-            .trim_whitespaces()
-            .to_any()
+    #[test]
+    fn trace_of_the_reader() {
+        let trace = trace("(a (b) 'c) )\n(1").to_text();
+        let reader = &trace[trace.find("list").unwrap()..];
+        assert_eq!(
+            reader,
+            "list 0..10\n  atom 1..2\n  list 3..6\n    atom 4..5\n  \
+             atom 7..8 failed at 7: unexpected quote\n  atom 8..9\n\
+             close 11..12 failed at 11: unexpected ')'\n\
+             list 13..15 failed at 15: expect ')'\n  atom 14..15\n"
+        );
     }
 }
@@ -0,0 +1,302 @@
+use std::{
+    collections::HashMap,
+    fmt::{Display, Write},
+};
+
+use super::expression::{Atom, Expression, ExpressionKind, Form, Operator};
+
+/// Prints each form on its own line. An expression is kept on one line if it
+/// fits in `width` characters, otherwise it is broken Lisp style, aligning
+/// arguments under the first one.
+pub(super) fn pretty_forms(forms: &[Form], width: usize) -> String {
+    let mut out = String::new();
+    for (idx, form) in forms.iter().enumerate() {
+        if idx > 0 {
+            out.push('\n');
+        }
+        match form {
+            Form::Define(binding) => {
+                let head = format!("(define {} ", binding.name);
+                let column = head.chars().count();
+                out.push_str(&head);
+                print_exp(&binding.exp, column, width, &mut out);
+                out.push(')');
+            }
+            Form::Exp(exp) => print_exp(exp, 0, width, &mut out),
+        }
+    }
+    out
+}
+
+// Printing is done on an explicit stack, deep nesting would overflow the
+// stack otherwise.
+enum Task<'a> {
+    // An expression starting at the column
+    Exp(&'a Expression, usize),
+    Str(String),
+    NewLine(usize),
+}
+
+fn print_exp(exp: &Expression, column: usize, width: usize, out: &mut String) {
+    let flat_lens = flat_lens(exp);
+    let mut stack = vec![Task::Exp(exp, column)];
+    while let Some(task) = stack.pop() {
+        let (exp, column) = match task {
+            Task::Exp(exp, column) => (exp, column),
+            Task::Str(str) => {
+                out.push_str(&str);
+                continue;
+            }
+            Task::NewLine(column) => {
+                new_line(column, out);
+                continue;
+            }
+        };
+        if column + flat_lens[&(exp as *const _)] <= width {
+            let _ = write!(out, "{}", exp);
+            continue;
+        }
+        // The tasks in order, pushed in reverse below
+        let mut tasks = Vec::new();
+        match &exp.kind {
+            ExpressionKind::Atom(_) | ExpressionKind::Error => {
+                let _ = write!(out, "{}", exp);
+            }
+            ExpressionKind::Oper { oper, lhs, rhs } => {
+                form_tasks(format!("({} ", oper), &[lhs, rhs], column, &mut tasks);
+            }
+            ExpressionKind::If {
+                pred,
+                then,
+                or_else,
+            } => {
+                form_tasks(
+                    "(if ".to_owned(),
+                    &[pred, then, or_else],
+                    column,
+                    &mut tasks,
+                );
+            }
+            ExpressionKind::Let { bindings, exp } => {
+                let head = "(let ";
+                let binding_column = column + head.len();
+                tasks.push(Task::Str(head.to_owned()));
+                for (idx, binding) in bindings.iter().enumerate() {
+                    if idx > 0 {
+                        tasks.push(Task::NewLine(binding_column));
+                    }
+                    let head = format!("({} ", binding.name);
+                    let exp_column = binding_column + head.chars().count();
+                    tasks.push(Task::Str(head));
+                    tasks.push(Task::Exp(&binding.exp, exp_column));
+                    tasks.push(Task::Str(")".to_owned()));
+                }
+                if bindings.is_empty() {
+                    tasks.push(Task::Exp(exp, binding_column));
+                } else {
+                    tasks.push(Task::NewLine(column + 2));
+                    tasks.push(Task::Exp(exp, column + 2));
+                }
+                tasks.push(Task::Str(")".to_owned()));
+            }
+        }
+        stack.extend(tasks.into_iter().rev());
+    }
+}
+
+fn form_tasks<'a>(head: String, args: &[&'a Expression], column: usize, tasks: &mut Vec<Task<'a>>) {
+    let arg_column = column + head.chars().count();
+    tasks.push(Task::Str(head));
+    for (idx, arg) in args.iter().enumerate() {
+        if idx > 0 {
+            tasks.push(Task::NewLine(arg_column));
+        }
+        tasks.push(Task::Exp(arg, arg_column));
+    }
+    tasks.push(Task::Str(")".to_owned()));
+}
+
+fn new_line(column: usize, out: &mut String) {
+    out.push('\n');
+    out.extend(std::iter::repeat_n(' ', column));
+}
+
+// Length in chars of every subexpression printed flat, computed once rather
+// than printing each of them again at every level.
+fn flat_lens(exp: &Expression) -> HashMap<*const Expression, usize> {
+    let mut lens = HashMap::new();
+    // Expressions with whether their children are done
+    let mut stack = vec![(exp, false)];
+    while let Some((exp, done)) = stack.pop() {
+        let children = children(exp);
+        if !done && !children.is_empty() {
+            stack.push((exp, true));
+            stack.extend(children.into_iter().map(|child| (child, false)));
+            continue;
+        }
+        let len = |exp: &Expression| lens[&(exp as *const _)];
+        let flat_len = match &exp.kind {
+            ExpressionKind::Atom(atom) => atom.to_string().chars().count(),
+            ExpressionKind::Error => "<error>".len(),
+            // (o l r)
+            ExpressionKind::Oper { lhs, rhs, .. } => len(lhs) + len(rhs) + 5,
+            // (if p t e)
+            ExpressionKind::If {
+                pred,
+                then,
+                or_else,
+            } => len(pred) + len(then) + len(or_else) + 6,
+            // (let (n e)... b)
+            ExpressionKind::Let { bindings, exp } => {
+                let bindings: usize = bindings
+                    .iter()
+                    .map(|binding| binding.name.chars().count() + len(&binding.exp) + 4)
+                    .sum();
+                bindings + len(exp) + 6
+            }
+        };
+        lens.insert(exp as *const _, flat_len);
+    }
+    lens
+}
+
+fn children(exp: &Expression) -> Vec<&Expression> {
+    match &exp.kind {
+        ExpressionKind::Atom(_) | ExpressionKind::Error => Vec::new(),
+        ExpressionKind::Oper { lhs, rhs, .. } => vec![lhs, rhs],
+        ExpressionKind::If {
+            pred,
+            then,
+            or_else,
+        } => vec![pred, then, or_else],
+        ExpressionKind::Let { bindings, exp } => bindings
+            .iter()
+            .map(|binding| &*binding.exp)
+            .chain([&**exp])
+            .collect(),
+    }
+}
+
+// Flat
+
+// Written from an explicit stack like `print_exp`.
+enum Piece<'a> {
+    Exp(&'a Expression),
+    Str(&'a str),
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut stack = vec![Piece::Exp(self)];
+        while let Some(piece) = stack.pop() {
+            let exp = match piece {
+                Piece::Exp(exp) => exp,
+                Piece::Str(str) => {
+                    f.write_str(str)?;
+                    continue;
+                }
+            };
+            match &exp.kind {
+                ExpressionKind::Atom(atom) => atom.fmt(f)?,
+                ExpressionKind::Error => f.write_str("<error>")?,
+                ExpressionKind::Oper { oper, lhs, rhs } => {
+                    write!(f, "({} ", oper)?;
+                    stack.extend([
+                        Piece::Str(")"),
+                        Piece::Exp(rhs),
+                        Piece::Str(" "),
+                        Piece::Exp(lhs),
+                    ]);
+                }
+                ExpressionKind::If {
+                    pred,
+                    then,
+                    or_else,
+                } => {
+                    f.write_str("(if ")?;
+                    stack.extend([
+                        Piece::Str(")"),
+                        Piece::Exp(or_else),
+                        Piece::Str(" "),
+                        Piece::Exp(then),
+                        Piece::Str(" "),
+                        Piece::Exp(pred),
+                    ]);
+                }
+                ExpressionKind::Let { bindings, exp } => {
+                    f.write_str("(let")?;
+                    stack.extend([Piece::Str(")"), Piece::Exp(exp), Piece::Str(" ")]);
+                    for binding in bindings.iter().rev() {
+                        stack.extend([
+                            Piece::Str(")"),
+                            Piece::Exp(&binding.exp),
+                            Piece::Str(" "),
+                            Piece::Str(&binding.name),
+                            Piece::Str(" ("),
+                        ]);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Atom {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Int(val) => val.fmt(f),
+            Self::Bool(val) => val.fmt(f),
+            Self::Var(id) => f.write_str(id),
+        }
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let oper = match self {
+            Self::Add => '+',
+            Self::Subtract => '-',
+            Self::Multiply => '*',
+            Self::Divide => '/',
+            Self::Eq => '=',
+            Self::Lt => '<',
+            Self::Gt => '>',
+        };
+        f.write_char(oper)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis;
+
+    #[test]
+    fn pretty() {
+        let source = "(define x (let (a 1) (b (+ a 2)) (if (< a b) (* a b) (- b a))))\n(+ x 1)";
+        assert_eq!(analysis::format(source, 80).unwrap(), source);
+        assert_eq!(
+            analysis::format(source, 30).unwrap(),
+            "(define x (let (a 1)\n".to_owned()
+                + "               (b (+ a 2))\n"
+                + "            (if (< a b)\n"
+                + "                (* a b)\n"
+                + "                (- b a))))\n"
+                + "(+ x 1)"
+        );
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let depth = 10_000;
+        let source = "(+ 1 ".repeat(depth) + "0" + &")".repeat(depth);
+        assert_eq!(analysis::format(&source, usize::MAX).unwrap(), source);
+        // Broken, every level is indented further, so the output is
+        // quadratic in the depth.
+        let source = "(+ 1 ".repeat(1_000) + "0" + &")".repeat(1_000);
+        let formatted = analysis::format(&source, 80).unwrap();
+        assert_eq!(formatted.lines().count(), 1_001);
+        let source = "(let (x 1) ".repeat(depth) + "x" + &")".repeat(depth);
+        assert_eq!(analysis::format(&source, usize::MAX).unwrap(), source);
+    }
+}
@@ -0,0 +1,163 @@
+use std::marker::PhantomData;
+
+use parsec::{Context, Parser, Result, SliceStream};
+
+use super::{
+    lexer::{Token, TokenKind},
+    Span,
+};
+
+/// Raw S-expression datum, read before any syntax checks.
+#[derive(Debug)]
+pub(super) struct Sexp {
+    pub(super) kind: SexpKind,
+    pub(super) span: Span,
+}
+
+#[derive(Debug)]
+pub(super) enum SexpKind {
+    Int(i64),
+    Bool(bool),
+    Symbol(String),
+    List(Vec<Sexp>),
+    // Bad input, whose error is reported already
+    Error,
+}
+
+/// Datum of the tokens, ending at char offset `end` of the source.
+pub(super) fn datum<'a>(end: usize) -> Datum<'a> {
+    Datum {
+        end,
+        marker: PhantomData,
+    }
+}
+
+/// Datum
+///
+/// Reads one datum from the tokens. Nested lists are built on an explicit
+/// stack, so nesting depth is only limited by the heap.
+///
+/// Syntax errors are reported to the `Context` and read as `Error` datums,
+/// so that reading goes on. It only fails where no datum is left. Errors are
+/// at char offsets of the source, like spans.
+#[derive(Debug, Clone)]
+pub(super) struct Datum<'a> {
+    // Char offset of the end of the source
+    end: usize,
+    marker: PhantomData<&'a [Token]>,
+}
+
+impl<'a> Parser for Datum<'a> {
+    type Input = SliceStream<'a, Token>;
+    type Output = Sexp;
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> Result<Self::Output> {
+        // Start offset and elements of every list being read.
+        let mut lists: Vec<(usize, Vec<Sexp>)> = Vec::new();
+        loop {
+            let Ok(Token { kind, span }) = context.next() else {
+                if lists.is_empty() {
+                    return Err(parsec::Error::new(self.end, "syntax error"));
+                }
+                // The open lists are closed at the end of the source.
+                let err = parsec::Error::new(self.end, "expect ')'");
+                context.report(err.clone());
+                let mut sexp = None;
+                while let Some((start, mut items)) = lists.pop() {
+                    trace_exit(context, self.end, Some(err.clone()));
+                    items.extend(sexp);
+                    sexp = Some(Sexp {
+                        kind: SexpKind::List(items),
+                        span: start..self.end,
+                    });
+                }
+                return Ok(sexp.unwrap());
+            };
+            let sexp = match kind {
+                TokenKind::LParen => {
+                    trace_enter(context, "list", span.start);
+                    lists.push((span.start, Vec::new()));
+                    continue;
+                }
+                TokenKind::RParen => match lists.pop() {
+                    Some((start, items)) => {
+                        trace_exit(context, span.end, None);
+                        Sexp {
+                            kind: SexpKind::List(items),
+                            span: start..span.end,
+                        }
+                    }
+                    None => {
+                        let err = parsec::Error::new(span.start, "unexpected ')'");
+                        trace_enter(context, "close", span.start);
+                        trace_exit(context, span.end, Some(err.clone()));
+                        context.report(err);
+                        Sexp {
+                            kind: SexpKind::Error,
+                            span,
+                        }
+                    }
+                },
+                kind => {
+                    let (kind, msg) = match kind {
+                        TokenKind::Int(val) => (SexpKind::Int(val), None),
+                        TokenKind::Bool(val) => (SexpKind::Bool(val), None),
+                        TokenKind::Ident(id) => (SexpKind::Symbol(id), None),
+                        TokenKind::Quote => (SexpKind::Error, Some("unexpected quote")),
+                        TokenKind::Str(_) => (SexpKind::Error, Some("unexpected string")),
+                        // Reported by the lexer already, yet failed in the
+                        // trace
+                        _ => (SexpKind::Error, None),
+                    };
+                    let err = match (&kind, msg) {
+                        (_, Some(msg)) => {
+                            let err = parsec::Error::new(span.start, msg);
+                            context.report(err.clone());
+                            Some(err)
+                        }
+                        (SexpKind::Error, None) => {
+                            Some(parsec::Error::new(span.start, "bad token"))
+                        }
+                        _ => None,
+                    };
+                    trace_enter(context, "atom", span.start);
+                    trace_exit(context, span.end, err);
+                    Sexp { kind, span }
+                }
+            };
+            match lists.last_mut() {
+                Some((_, items)) => items.push(sexp),
+                None => return Ok(sexp),
+            }
+        }
+    }
+}
+
+// The reader runs no named parsers, it records its lists and atoms itself,
+// at char offsets like the lexer.
+fn trace_enter(context: &mut Context<SliceStream<Token>>, name: &'static str, start: usize) {
+    if let Some(trace) = context.trace_mut() {
+        trace.enter(name, start);
+    }
+}
+
+fn trace_exit(context: &mut Context<SliceStream<Token>>, end: usize, error: Option<parsec::Error>) {
+    if let Some(trace) = context.trace_mut() {
+        trace.exit(end, error);
+    }
+}
+
+// Lists are dropped iteratively, deep nesting would overflow the stack.
+impl Drop for Sexp {
+    fn drop(&mut self) {
+        let SexpKind::List(items) = &mut self.kind else {
+            return;
+        };
+        let mut stack = std::mem::take(items);
+        while let Some(mut sexp) = stack.pop() {
+            if let SexpKind::List(items) = &mut sexp.kind {
+                stack.append(items);
+            }
+        }
+    }
+}
@@ -1,9 +1,12 @@
-use std::{borrow::Cow, fmt::Display};
+use std::{borrow::Cow, fmt::Display, ops::Range};
 
 use parsec;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Character offsets into the source, `start..end`.
+pub type Span = Range<usize>;
+
 #[derive(Debug, Clone, Copy)]
 pub enum Val {
     Int(i64),
@@ -31,21 +34,87 @@ impl Display for Val {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Error {
     Parser(parsec::Error),
-    TypeMismatch,
-    UnboundIdentifier,
-    DivisionByZero,
+    TypeMismatch(Span),
+    UnboundIdentifier(Span),
+    DivisionByZero(Span),
+    /// An integer result out of the range of `i64`
+    Overflow(Span),
+    StepLimitExceeded(Span),
+    DepthLimitExceeded(Span),
+    ValueLimitExceeded(Span),
+    Timeout(Span),
+    Interrupted(Span),
+}
+
+impl Error {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Parser(_) => "Parser",
+            Self::TypeMismatch(_) => "TypeMismatch",
+            Self::UnboundIdentifier(_) => "UnboundIdentifier",
+            Self::DivisionByZero(_) => "DivisionByZero",
+            Self::Overflow(_) => "Overflow",
+            Self::StepLimitExceeded(_) => "StepLimitExceeded",
+            Self::DepthLimitExceeded(_) => "DepthLimitExceeded",
+            Self::ValueLimitExceeded(_) => "ValueLimitExceeded",
+            Self::Timeout(_) => "Timeout",
+            Self::Interrupted(_) => "Interrupted",
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Parser(err) => err.pos..err.pos,
+            Self::TypeMismatch(span)
+            | Self::UnboundIdentifier(span)
+            | Self::DivisionByZero(span)
+            | Self::Overflow(span)
+            | Self::StepLimitExceeded(span)
+            | Self::DepthLimitExceeded(span)
+            | Self::ValueLimitExceeded(span)
+            | Self::Timeout(span)
+            | Self::Interrupted(span) => span.clone(),
+        }
+    }
+
+    /// The error moved `offset` chars on, e.g. from the text of a form to
+    /// the whole input it was read from.
+    pub fn offset_by(self, offset: usize) -> Self {
+        let shift = |span: Span| span.start + offset..span.end + offset;
+        match self {
+            Self::Parser(err) => Self::Parser(parsec::Error {
+                pos: err.pos + offset,
+                ..err
+            }),
+            Self::TypeMismatch(span) => Self::TypeMismatch(shift(span)),
+            Self::UnboundIdentifier(span) => Self::UnboundIdentifier(shift(span)),
+            Self::DivisionByZero(span) => Self::DivisionByZero(shift(span)),
+            Self::Overflow(span) => Self::Overflow(shift(span)),
+            Self::StepLimitExceeded(span) => Self::StepLimitExceeded(shift(span)),
+            Self::DepthLimitExceeded(span) => Self::DepthLimitExceeded(shift(span)),
+            Self::ValueLimitExceeded(span) => Self::ValueLimitExceeded(shift(span)),
+            Self::Timeout(span) => Self::Timeout(shift(span)),
+            Self::Interrupted(span) => Self::Interrupted(shift(span)),
+        }
+    }
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let desc: Cow<'static, str> = match self {
             Self::Parser(err) => format!("{}", err).into(),
-            Self::TypeMismatch => "Type Mismatch".into(),
-            Self::UnboundIdentifier => "Unbound Identifier".into(),
-            Self::DivisionByZero => "Division By Zero".into(),
+            Self::TypeMismatch(_) => "Type Mismatch".into(),
+            Self::UnboundIdentifier(_) => "Unbound Identifier".into(),
+            Self::DivisionByZero(_) => "Division By Zero".into(),
+            Self::Overflow(_) => "Overflow".into(),
+            Self::StepLimitExceeded(_) => "Step Limit Exceeded".into(),
+            Self::DepthLimitExceeded(_) => "Depth Limit Exceeded".into(),
+            Self::ValueLimitExceeded(_) => "Value Limit Exceeded".into(),
+            Self::Timeout(_) => "Timeout".into(),
+            Self::Interrupted(_) => "Interrupted".into(),
         };
         f.write_str(&desc)
     }
@@ -0,0 +1,115 @@
+use super::{
+    evaluator,
+    expression::{Form, Globals},
+    parser, Error, Limits, ParsedForm, Result, Val,
+};
+
+const KEYWORDS: [&str; 5] = ["define", "false", "if", "let", "true"];
+
+/// An interpreter session keeping top-level definitions across executions.
+///
+/// A source passed to [`Session::execute`] is a sequence of forms, each
+/// either an expression or a `(define x a)`, which evaluates `a` and binds
+/// its value to `x` for the rest of the session.
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    globals: Globals,
+    execution_count: usize,
+    limits: Limits,
+}
+
+/// Completion candidates for the identifier ending at the cursor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    /// Character offset where the completed identifier starts.
+    pub start: usize,
+    pub matches: Vec<String>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Evaluates the forms of `source` in order, returning the value of the
+    /// last one, or `None` if it is a definition or there are no forms.
+    ///
+    /// Stops at the first error; definitions made before it are kept.
+    pub fn execute(&mut self, source: impl AsRef<str>) -> Result<Option<Val>> {
+        self.execution_count += 1;
+        let forms = parser::parse_forms(source.as_ref()).map_err(Error::Parser)?;
+        self.eval_forms(&forms)
+    }
+
+    /// Like [`Session::execute`], for a form parsed already, e.g. one read
+    /// by a [`FormStream`]. Errors are at char offsets of the whole input the
+    /// form was read from.
+    ///
+    /// [`FormStream`]: crate::FormStream
+    pub fn execute_form(&mut self, form: &ParsedForm) -> Result<Option<Val>> {
+        self.execution_count += 1;
+        if let Some(err) = form.errors().first() {
+            return Err(err.clone());
+        }
+        self.eval_forms(&form.forms)
+            .map_err(|err| err.offset_by(form.source.span.start))
+    }
+
+    fn eval_forms(&mut self, forms: &[Form]) -> Result<Option<Val>> {
+        let mut last = None;
+        for form in forms {
+            last = match form {
+                Form::Define(binding) => {
+                    let val =
+                        evaluator::eval_with_globals(&binding.exp, &self.globals, &self.limits)?;
+                    self.globals.insert(binding.name.clone(), val);
+                    None
+                }
+                Form::Exp(exp) => Some(evaluator::eval_with_globals(
+                    exp,
+                    &self.globals,
+                    &self.limits,
+                )?),
+            };
+        }
+        Ok(last)
+    }
+
+    /// Limits applied to each form evaluated by [`Session::execute`].
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Number of times [`Session::execute`] has been called.
+    pub fn execution_count(&self) -> usize {
+        self.execution_count
+    }
+
+    pub fn get(&self, name: &str) -> Option<Val> {
+        self.globals.get(name).copied()
+    }
+
+    /// Completes the identifier ending at character offset `cursor` with
+    /// keywords and defined names.
+    pub fn complete(&self, source: &str, cursor: usize) -> Completion {
+        let chars: Vec<char> = source.chars().take(cursor).collect();
+        let start = chars
+            .iter()
+            .rposition(|c| !(c.is_ascii_alphanumeric() || *c == '_'))
+            .map_or(0, |idx| idx + 1);
+        let prefix: String = chars[start..].iter().collect();
+        let mut matches: Vec<String> = KEYWORDS
+            .iter()
+            .map(|keyword| keyword.to_string())
+            .chain(self.globals.keys().cloned())
+            .filter(|name| name.starts_with(&prefix))
+            .collect();
+        matches.sort();
+        matches.dedup();
+        Completion { start, matches }
+    }
+}
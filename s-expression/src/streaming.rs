@@ -0,0 +1,199 @@
+use std::{
+    io::{self, Read},
+    sync::OnceLock,
+};
+
+use parsec::{consumers::*, Context, Parser, PartialStream, Status, StreamParser, SyncAny};
+
+use super::{
+    expression::Form,
+    lexer::{spaces, token, Token, TokenKind},
+    parser, Error, Span,
+};
+
+/// Reads top-level forms as the source arrives, e.g. line by line from a
+/// terminal or chunk by chunk from a socket, so each can be evaluated as soon
+/// as it is complete.
+///
+/// Forms are only delimited here, syntax errors are left to
+/// [`SourceForm::parse`] or to the evaluation of their text.
+///
+/// ```
+/// use s_expression::{FormReader, FormStatus};
+///
+/// let mut reader = FormReader::new();
+/// reader.feed("(+ 1\n");
+/// assert!(matches!(reader.next_form(), FormStatus::Incomplete));
+/// reader.feed("2) x");
+/// let FormStatus::Form(form) = reader.next_form() else { panic!() };
+/// assert_eq!((form.text.as_str(), form.span), ("(+ 1\n2)", 0..7));
+/// // `x` may go on.
+/// assert!(matches!(reader.next_form(), FormStatus::Incomplete));
+/// reader.finish();
+/// let FormStatus::Form(form) = reader.next_form() else { panic!() };
+/// assert_eq!((form.text.as_str(), form.span), ("x", 8..9));
+/// assert!(matches!(reader.next_form(), FormStatus::End));
+/// ```
+#[derive(Clone)]
+pub struct FormReader {
+    parser: StreamParser<SyncAny<'static, PartialStream, Option<SourceForm>>>,
+}
+
+/// Source of a top-level form, spanning char offsets of the whole input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceForm {
+    pub text: String,
+    pub span: Span,
+}
+
+impl SourceForm {
+    pub fn parse(self) -> ParsedForm {
+        let (forms, errors) = parser::parse_forms_partial(&self.text);
+        let errors = errors
+            .into_iter()
+            .map(|err| Error::Parser(err).offset_by(self.span.start))
+            .collect();
+        ParsedForm {
+            source: self,
+            forms,
+            errors,
+        }
+    }
+}
+
+/// A top-level form parsed, ready for [`Session::execute_form`].
+///
+/// [`Session::execute_form`]: crate::Session::execute_form
+#[derive(Debug, Clone)]
+pub struct ParsedForm {
+    pub source: SourceForm,
+    // Spanning char offsets of the text of the form
+    pub(super) forms: Vec<Form>,
+    errors: Vec<Error>,
+}
+
+impl ParsedForm {
+    /// Syntax errors of the form, at char offsets of the whole input.
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormStatus {
+    Form(SourceForm),
+    /// The next form is cut short, or there may be one.
+    Incomplete,
+    End,
+    /// The input could not be split into forms, nothing more can be read.
+    Failed(parsec::Error),
+}
+
+impl FormReader {
+    pub fn new() -> Self {
+        Self {
+            parser: StreamParser::new(form_parser().clone()),
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &str) {
+        self.parser.feed(chunk)
+    }
+
+    /// Marks the end of the source.
+    pub fn finish(&mut self) {
+        self.parser.finish()
+    }
+
+    /// Feeds one chunk read from `reader`, and finishes the source when it
+    /// is exhausted. Returns the number of bytes read.
+    pub fn read_from(&mut self, reader: &mut impl Read) -> io::Result<usize> {
+        self.parser.read_from(reader)
+    }
+
+    pub fn next_form(&mut self) -> FormStatus {
+        let status = match self.parser.parse_next() {
+            Status::Parsed(Some(form)) => FormStatus::Form(form),
+            Status::Parsed(None) => FormStatus::End,
+            Status::Incomplete => FormStatus::Incomplete,
+            Status::Failed(err) => FormStatus::Failed(err),
+        };
+        // Reported again when the form is evaluated.
+        self.parser.take_errors();
+        status
+    }
+
+    /// Whether part of a form has been read, as opposed to whitespaces or
+    /// nothing at all.
+    pub fn is_pending(&self) -> bool {
+        !self.parser.pending().trim().is_empty()
+    }
+
+    // Bytes of the input read but not returned yet.
+    #[cfg(feature = "tokio")]
+    pub(super) fn pending_len(&self) -> usize {
+        self.parser.pending().len()
+    }
+}
+
+impl Default for FormReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Built once and shared by every thread.
+fn form_parser() -> &'static SyncAny<'static, PartialStream, Option<SourceForm>> {
+    static PARSER: OnceLock<SyncAny<'static, PartialStream, Option<SourceForm>>> = OnceLock::new();
+    PARSER.get_or_init(|| {
+        let form = Balanced {
+            token: spaces().use_right(token::<PartialStream>()),
+        }
+        .recognize()
+        .spanned()
+        .map(|(text, span)| {
+            Some(SourceForm {
+                text: text.to_string(),
+                span,
+            })
+        });
+        spaces()
+            .use_right(eos().map(|_| None).or(form))
+            .to_sync_any()
+    })
+}
+
+/// Balanced
+///
+/// Reads tokens up to the `)` closing the first one, if it is a `(`. At the
+/// end of the source the open lists are left for the reader to report.
+#[derive(Debug, Clone)]
+struct Balanced<P> {
+    token: P,
+}
+
+impl<P> Parser for Balanced<P>
+where
+    P: Parser<Output = Token>,
+{
+    type Input = P::Input;
+    type Output = ();
+
+    fn parse_raw(&self, context: &mut Context<Self::Input>) -> parsec::Result<Self::Output> {
+        let mut depth = 0usize;
+        loop {
+            match self.token.parse(context) {
+                Ok(Token { kind, .. }) => match kind {
+                    TokenKind::LParen => depth += 1,
+                    TokenKind::RParen => depth = depth.saturating_sub(1),
+                    _ => {}
+                },
+                Err(_) if depth > 0 => return Ok(()),
+                Err(err) => return Err(err),
+            }
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+    }
+}